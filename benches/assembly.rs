@@ -0,0 +1,81 @@
+//! Serial vs parallel assembly of the mass matrix and centripetal vector at n = 30.
+//! Run with `cargo bench --bench assembly`.
+use std::hint::black_box;
+use std::time::Instant;
+
+#[path = "../src/math.rs"]
+#[allow(dead_code)]
+mod math;
+
+use math::{NPendulumMath, PARALLEL_THRESHOLD};
+
+const N: usize = 30;
+const ITERATIONS: u32 = 2_000;
+
+fn chain() -> NPendulumMath<f64> {
+    let mut masses = vec![0.0];
+    let mut lengths = vec![0.0];
+    let mut angles = vec![0.0];
+    let mut ang_vels = vec![0.0];
+    for i in 1..=N {
+        masses.push(1.0 + 0.01 * i as f64);
+        lengths.push(1.0 / N as f64);
+        angles.push(0.1 * i as f64);
+        ang_vels.push(0.05 * i as f64);
+    }
+    NPendulumMath::new(N, masses, lengths, angles, ang_vels)
+}
+
+/// Reference serial assembly, entry by entry in row-major order.
+fn assemble_serial(p: &NPendulumMath<f64>) -> (Vec<f64>, Vec<f64>) {
+    let mut m = Vec::with_capacity(N * N);
+    let mut c = Vec::with_capacity(N);
+    for i in 1..=N {
+        for j in 1..=N {
+            let k = i.max(j);
+            let m_val: f64 = p.masses[k..=N].iter().fold(0.0, |acc, &m| acc + m);
+            m.push(m_val * p.lengths[i] * p.lengths[j] * (p.angles[i] - p.angles[j]).cos());
+        }
+        let mut f_term = 0.0;
+        for j in 1..=N {
+            let m_val: f64 = p.masses[i.max(j)..=N].iter().fold(0.0, |acc, &m| acc + m);
+            f_term += m_val
+                * p.lengths[i]
+                * p.lengths[j]
+                * (p.angles[i] - p.angles[j]).sin()
+                * (p.ang_vels[j] * p.ang_vels[j]);
+        }
+        c.push(f_term);
+    }
+    (m, c)
+}
+
+fn time<F: FnMut()>(label: &str, mut f: F) {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_iter = start.elapsed() / ITERATIONS;
+    println!("{label:<10} {per_iter:?} per assembly");
+}
+
+fn main() {
+    const { assert!(N > PARALLEL_THRESHOLD, "n must exceed the parallel threshold") };
+    let p = chain();
+
+    let (m_ref, c_ref) = assemble_serial(&p);
+    let (m, c) = (p.set_mass_matrix(), p.set_centripetal_matrix());
+    assert_eq!(m.transpose().as_slice(), m_ref.as_slice(), "mass matrix differs from serial");
+    assert_eq!(c.as_slice(), c_ref.as_slice(), "centripetal vector differs from serial");
+
+    println!("n = {N}, {ITERATIONS} iterations");
+    time("serial", || {
+        black_box(assemble_serial(black_box(&p)));
+    });
+    time("parallel", || {
+        black_box((
+            black_box(&p).set_mass_matrix(),
+            black_box(&p).set_centripetal_matrix(),
+        ));
+    });
+}
@@ -0,0 +1,61 @@
+//! Heap allocations and wall-clock time of a full `solve_from_state` run (n = 10,
+//! 2000 steps). Run with `cargo bench --bench solve`.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+#[path = "../src/error.rs"]
+#[allow(dead_code)]
+mod error;
+#[path = "../src/logic.rs"]
+#[allow(dead_code)]
+mod logic;
+#[path = "../src/math.rs"]
+#[allow(dead_code)]
+mod math;
+
+use logic::{NPendulumSolver, SolveOptions};
+
+/// Counts every allocation made through the global allocator.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const N: usize = 10;
+const N_POINTS: usize = 2_000;
+const RUNS: u32 = 20;
+
+fn main() {
+    let solver = NPendulumSolver::new(N, vec![1.0; N + 1], vec![1.0 / N as f64; N + 1]);
+    let angles: Vec<f64> = (0..=N).map(|i| 0.1 * i as f64).collect();
+    let y0 = solver.initial_state(&angles, &[0.0; N + 1]).unwrap();
+    // Record only the last frame so the count reflects the integrator, not the output
+    let opts = SolveOptions { stride: N_POINTS, ..SolveOptions::default() };
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        let traj = solver.solve_from_state(black_box(y0.clone()), 10.0, N_POINTS, &opts);
+        black_box(traj.expect("solve failed"));
+    }
+    let elapsed = start.elapsed() / RUNS;
+    let allocations = (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / RUNS as f64;
+
+    println!("n = {N}, {N_POINTS} steps, {RUNS} runs");
+    println!("{:.1} allocations per step, {elapsed:?} per run", allocations / N_POINTS as f64);
+}
@@ -0,0 +1,85 @@
+// src/cache.rs
+use actix_web::web::Bytes;
+use lru::LruCache;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+/// An encoded response body and the content type it was encoded as.
+#[derive(Clone)]
+pub struct CachedBody {
+    pub content_type: &'static str,
+    pub bytes: Bytes,
+}
+
+/// LRU of encoded `/simulate` responses, keyed on the normalized request (see `key`).
+/// Built once in `main` and shared by every worker as app data; a capacity of 0 disables it.
+pub struct ResponseCache {
+    entries: Option<Mutex<LruCache<u64, CachedBody>>>,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize) -> Self {
+        let entries = NonZeroUsize::new(capacity).map(|cap| Mutex::new(LruCache::new(cap)));
+        ResponseCache { entries }
+    }
+
+    /// Hash of a normalized request encoding plus the response format, so the JSON and
+    /// MessagePack bodies of one request are cached separately.
+    pub fn key(normalized: &[u8], content_type: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        normalized.hash(&mut hasher);
+        content_type.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The cached body for `key`, marking it most recently used.
+    pub fn get(&self, key: u64) -> Option<CachedBody> {
+        // A panic while holding the lock cannot leave the LRU half-updated, so keep using it
+        let entries = self.entries.as_ref()?;
+        let mut entries = entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        entries.get(&key).cloned()
+    }
+
+    /// Stores `body` under `key`, evicting the least recently used entry when full.
+    pub fn insert(&self, key: u64, body: CachedBody) {
+        if let Some(entries) = &self.entries {
+            let mut entries = entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            entries.put(key, body);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(text: &'static str) -> CachedBody {
+        CachedBody { content_type: "application/json", bytes: Bytes::from_static(text.as_bytes()) }
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let cache = ResponseCache::new(2);
+        let [a, b, c] = [b"a", b"b", b"c"].map(|k| ResponseCache::key(k, "application/json"));
+        cache.insert(a, body("A"));
+        cache.insert(b, body("B"));
+        assert!(cache.get(a).is_some()); // a is now more recent than b
+        cache.insert(c, body("C"));
+        assert!(cache.get(b).is_none());
+        assert_eq!(cache.get(a).unwrap().bytes, "A");
+        assert_eq!(cache.get(c).unwrap().bytes, "C");
+    }
+
+    #[test]
+    fn formats_are_cached_separately_and_zero_capacity_disables() {
+        assert_ne!(
+            ResponseCache::key(b"{}", "application/json"),
+            ResponseCache::key(b"{}", "application/msgpack")
+        );
+        let disabled = ResponseCache::new(0);
+        disabled.insert(1, body("A"));
+        assert!(disabled.get(1).is_none());
+    }
+}
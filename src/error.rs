@@ -0,0 +1,100 @@
+// src/error.rs
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+/// Errors reported to the client by the simulation endpoints.
+/// Serialized as `{ "code": "...", "detail": "..." }` so clients can branch on `code`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimError {
+    /// A CSV field (or state vector) has the wrong number of values (too many or too few).
+    LengthMismatch { field: &'static str, expected: usize, got: usize },
+    /// A CSV token could not be parsed as a number.
+    ParseError { field: &'static str, token: String },
+    /// A parameter exceeds the server's size limits.
+    TooLarge { field: &'static str, max: usize, got: usize },
+    /// A parameter is present but has an unusable value.
+    InvalidParam { field: &'static str, reason: String },
+    /// The mass matrix could not be inverted during integration.
+    SingularMatrix,
+    /// An iterative solver did not reach its tolerance.
+    NotConverged { iterations: usize },
+    /// The simulation ran past the server's time limit and was aborted.
+    TimedOut { limit_secs: f64 },
+    /// The integration went numerically unstable (non-finite or runaway velocities).
+    BlewUp { time: f64 },
+    /// An output came out as NaN or infinite, which JSON cannot represent.
+    NonFinite { field: &'static str },
+    /// The request body exceeds the server's size limit (bytes).
+    BodyTooLarge { limit: usize },
+    /// The client sent requests faster than the server's rate limit allows.
+    RateLimited { retry_after_secs: u64 },
+}
+
+impl SimError {
+    /// Stable machine-readable identifier for the error kind.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SimError::LengthMismatch { .. } => "length_mismatch",
+            SimError::ParseError { .. } => "parse_error",
+            SimError::TooLarge { .. } => "too_large",
+            SimError::InvalidParam { .. } => "invalid_param",
+            SimError::SingularMatrix => "singular_matrix",
+            SimError::NotConverged { .. } => "not_converged",
+            SimError::TimedOut { .. } => "timed_out",
+            SimError::BlewUp { .. } => "blew_up",
+            SimError::NonFinite { .. } => "non_finite",
+            SimError::BodyTooLarge { .. } => "body_too_large",
+            SimError::RateLimited { .. } => "rate_limited",
+        }
+    }
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::LengthMismatch { field, expected, got } => {
+                let problem = if got > expected { "too many values" } else { "too few values" };
+                write!(f, "{field}: {problem} (expected {expected}, got {got})")
+            }
+            SimError::ParseError { field, token } => {
+                write!(f, "{field}: could not parse '{token}' as a number")
+            }
+            SimError::TooLarge { field, max, got } => {
+                write!(f, "{field}: {got} exceeds the maximum of {max}")
+            }
+            SimError::InvalidParam { field, reason } => write!(f, "{field}: {reason}"),
+            SimError::SingularMatrix => {
+                write!(f, "the mass matrix became singular (degenerate masses or lengths)")
+            }
+            SimError::NotConverged { iterations } => {
+                write!(f, "did not converge within {iterations} iterations")
+            }
+            SimError::TimedOut { limit_secs } => {
+                write!(f, "the simulation exceeded the {limit_secs} s time limit")
+            }
+            SimError::BlewUp { time } => {
+                write!(f, "the state blew up at t = {time} s (non-finite or runaway velocities)")
+            }
+            SimError::NonFinite { field } => {
+                write!(f, "{field}: the result contains NaN or infinite values")
+            }
+            SimError::BodyTooLarge { limit } => {
+                write!(f, "the request body exceeds the limit of {limit} bytes")
+            }
+            SimError::RateLimited { retry_after_secs } => {
+                write!(f, "too many requests; retry in {retry_after_secs} s")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimError {}
+
+impl Serialize for SimError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("SimError", 2)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("detail", &self.to_string())?;
+        s.end()
+    }
+}
@@ -0,0 +1,16 @@
+// src/lib.rs
+//! N-pendulum solver and its `/simulate` request handling. The HTTP server (`main.rs`) is
+//! behind the default `server` feature; `wasm` adds a browser entry point instead.
+pub mod error;
+pub mod logic;
+pub mod math;
+pub mod ui;
+
+#[cfg(feature = "server")]
+pub mod cache;
+#[cfg(feature = "server")]
+pub mod metrics;
+#[cfg(feature = "server")]
+pub mod ratelimit;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -1,51 +1,604 @@
-use crate::math::NPendulumMath;
-use nalgebra::{DVector};
+use crate::error::SimError;
+use crate::math::{CentripetalForm, GravityModel, NPendulumMath, SpringPendulumMath};
+use nalgebra::{DMatrix, DVector, RealField};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::time::Instant;
 
-pub struct NPendulumSolver {
+/// Which sign change of the crossing variable counts as a section crossing.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossingDirection {
+    /// Crossing from negative to positive (positive velocity).
+    #[default]
+    Positive,
+    /// Crossing from positive to negative (negative velocity).
+    Negative,
+    /// Either direction.
+    Both,
+}
+
+/// One classic RK4 step of dy/dt = f(t, y) from `t` to `t + dt`.
+/// The stages are evaluated at t, t + dt/2, t + dt/2 and t + dt.
+fn rk4<T, F>(f: F, t: T, y: &DVector<T>, dt: T) -> Result<DVector<T>, SimError>
+where
+    T: RealField + Copy,
+    F: Fn(T, &DVector<T>) -> Result<DVector<T>, SimError>,
+{
+    let half: T = nalgebra::convert(0.5);
+    let two: T = nalgebra::convert(2.0);
+    let six: T = nalgebra::convert(6.0);
+    let t_mid = t + dt * half;
+
+    let k1 = f(t, y)?;
+    let k2 = f(t_mid, &(y + &k1 * (dt * half)))?;
+    let k3 = f(t_mid, &(y + &k2 * (dt * half)))?;
+    let k4 = f(t + dt, &(y + &k3 * dt))?;
+
+    Ok(y + (k1 + k2 * two + k3 * two + k4) * (dt / six))
+}
+
+/// Floating-point precision used for the integration.
+/// `F32` roughly halves the size of the integrator's state at the cost of accuracy:
+/// single precision carries ~7 significant digits, so chaotic runs diverge from the `F64`
+/// result within a few seconds of simulated time and conserved quantities drift visibly.
+/// Results are widened back to `f64` for post-processing and serialization.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    #[default]
+    F64,
+    F32,
+}
+
+/// Joint friction law, selected per request: the generalized torque Q_i acting on link i
+/// with per-link coefficient c_i.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DampingModel {
+    #[default]
+    None,
+    /// Q = −b ω (viscous).
+    Linear,
+    /// Q = −c ω|ω| (aerodynamic drag).
+    Quadratic,
+    /// Q = −μ sign(ω) (dry friction), regularized near rest (see `COULOMB_REST_SPEED`).
+    Coulomb,
+}
+
+/// Below this |ω| (rad/s) Coulomb friction ramps linearly to zero instead of flipping sign
+/// every step, which would make the integrator chatter around ω = 0.
+const COULOMB_REST_SPEED: f64 = 1e-3;
+
+/// Friction model plus its per-link coefficients, [0, c1, ..., cn].
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Damping<T> {
+    pub model: DampingModel,
+    pub coefficients: Vec<T>,
+}
+
+impl<T: RealField + Copy> Damping<T> {
+    /// Generalized friction torque on link `i` (1-based) turning at `omega`.
+    fn torque(&self, i: usize, omega: T) -> T {
+        let c = self.coefficients[i];
+        match self.model {
+            DampingModel::None => T::zero(),
+            DampingModel::Linear => -c * omega,
+            DampingModel::Quadratic => -c * omega * omega.abs(),
+            DampingModel::Coulomb => {
+                let rest: T = nalgebra::convert(COULOMB_REST_SPEED);
+                -c * (omega / rest).clamp(-T::one(), T::one())
+            }
+        }
+    }
+}
+
+/// Rule used by `integrate` for time-integrals over the recorded frames.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Quadrature {
+    #[default]
+    Trapezoidal,
+    Simpson,
+}
+
+/// Bob-contact detection performed on every integration step.
+#[derive(Clone, Copy, Debug)]
+pub struct CollisionCheck<T> {
+    /// Two non-adjacent bobs closer than this count as a collision.
+    pub min_separation: T,
+    /// Stop integrating (truncating the trajectory) at the first collision.
+    pub stop: bool,
+}
+
+/// Run-control settings for `solve` (the physics lives on `NPendulumSolver`).
+#[derive(Clone, Debug)]
+pub struct SolveOptions<T> {
+    /// Record every `stride`-th integration step (the final step is always recorded).
+    pub stride: usize,
+    /// Optional bob-contact detection.
+    pub collision: Option<CollisionCheck<T>>,
+    /// RK4 substeps of `dt / substeps` per integration step. Only accuracy changes: the
+    /// steps, and therefore the recorded frames and collision checks, stay at `dt` spacing.
+    pub substeps: usize,
+    /// Abort with `SimError::TimedOut` once this instant has passed (checked every
+    /// `DEADLINE_CHECK_STEPS` steps).
+    pub deadline: Option<Deadline>,
+    /// After every integration step, rescale the angular velocities so the total energy
+    /// returns to its initial value (energy projection against RK4's slow drift).
+    pub energy_correction: bool,
+    /// Stop once any |ω_i| exceeds this (rad/s). Non-finite states always stop the run.
+    pub max_ang_vel: Option<T>,
+    /// Integrate forward this long (s) before the first recorded frame, so `t_axis` starts
+    /// there. The burn-in steps are not recorded, observed or collision-checked.
+    pub warmup: Option<T>,
+}
+
+impl<T> Default for SolveOptions<T> {
+    fn default() -> Self {
+        Self {
+            stride: 1,
+            collision: None,
+            substeps: 1,
+            deadline: None,
+            energy_correction: false,
+            max_ang_vel: None,
+            warmup: None,
+        }
+    }
+}
+
+/// Wall-clock limit for a solve, plus the configured limit for the error message.
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline {
+    pub at: Instant,
+    pub limit_secs: f64,
+}
+
+/// How many integration steps run between two deadline checks.
+const DEADLINE_CHECK_STEPS: usize = 64;
+
+/// Fails with `SimError::TimedOut` if `step` is a check step and the deadline has passed.
+fn check_deadline(deadline: Option<Deadline>, step: usize) -> Result<(), SimError> {
+    match deadline {
+        Some(d) if step.is_multiple_of(DEADLINE_CHECK_STEPS) && Instant::now() >= d.at => {
+            Err(SimError::TimedOut { limit_secs: d.limit_secs })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// True if the state has gone non-finite or an angular velocity (the entries in `ang_vels`)
+/// exceeds `max_ang_vel`: the integration has gone numerically unstable.
+fn blew_up<T: RealField + Copy>(
+    y: &DVector<T>,
+    ang_vels: std::ops::Range<usize>,
+    max_ang_vel: Option<T>,
+) -> bool {
+    y.iter().any(|v| !v.is_finite())
+        || max_ang_vel.is_some_and(|max| y.rows_range(ang_vels).iter().any(|w| w.abs() > max))
+}
+
+/// SplitMix64: a tiny seeded generator, so sampled initial conditions depend only on the
+/// seed (and stay the same across dependency upgrades).
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [lo, hi).
+    fn uniform(&mut self, lo: f64, hi: f64) -> f64 {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        lo + (hi - lo) * unit
+    }
+
+    /// Standard normal sample (Box–Muller; the first uniform is kept away from 0).
+    fn gaussian(&mut self) -> f64 {
+        let radius = (-2.0 * (1.0 - self.uniform(0.0, 1.0)).ln()).sqrt();
+        radius * (2.0 * std::f64::consts::PI * self.uniform(0.0, 1.0)).cos()
+    }
+}
+
+/// Adds independent N(0, σ²) noise to every value, seeded so the same `seed` always gives
+/// the same perturbation (ensemble runs around a nominal initial state).
+pub fn perturb(values: &mut [f64], sigma: f64, seed: u64) {
+    let mut rng = SplitMix64(seed);
+    for value in values {
+        *value += sigma * rng.gaussian();
+    }
+}
+
+/// Output of `solve`: the recorded frames plus events detected while integrating.
+pub struct Trajectory<T = f64> {
+    pub t_axis: Vec<T>,
+    pub sol: Vec<DVector<T>>,
+    pub first_collision_time: Option<T>,
+    /// Time at which the run was stopped as numerically unstable (see `blew_up`); the
+    /// recorded frames end before it.
+    pub blew_up_at_time: Option<T>,
+}
+
+impl Trajectory {
+    /// State at an arbitrary time `t`, interpolated between the recorded frames.
+    /// Angles use cubic Hermite interpolation (the stored ω are their exact derivatives);
+    /// angular velocities are interpolated linearly. Returns `None` outside the recorded span.
+    /// The time axis may run either way (backward runs have it decreasing).
+    pub fn state_at(&self, t: f64) -> Option<DVector<f64>> {
+        let (&t_start, &t_end) = (self.t_axis.first()?, self.t_axis.last()?);
+        let (t_min, t_max) = (t_start.min(t_end), t_start.max(t_end));
+        // Accumulated time steps can land a hair short of t_max
+        let tol = 1e-9 * (t_max - t_min).max(1.0);
+        if t < t_min - tol || t > t_max + tol {
+            return None;
+        }
+        let t = t.clamp(t_min, t_max);
+
+        if self.t_axis.len() == 1 {
+            return Some(self.sol[0].clone());
+        }
+
+        // First frame strictly past t in the direction of integration (or the last frame)
+        let forward = t_end >= t_start;
+        let hi = self
+            .t_axis
+            .partition_point(|&ti| if forward { ti <= t } else { ti >= t })
+            .clamp(1, self.t_axis.len() - 1);
+        let lo = hi - 1;
+        let (y0, y1) = (&self.sol[lo], &self.sol[hi]);
+        let h = self.t_axis[hi] - self.t_axis[lo];
+        let s = (t - self.t_axis[lo]) / h;
+
+        let n = y0.len() / 2;
+        let (s2, s3) = (s * s, s * s * s);
+        let (h00, h10) = (2.0 * s3 - 3.0 * s2 + 1.0, s3 - 2.0 * s2 + s);
+        let (h01, h11) = (-2.0 * s3 + 3.0 * s2, s3 - s2);
+
+        let mut y = y0 + (y1 - y0) * s;
+        for k in 0..n {
+            y[k] = h00 * y0[k] + h10 * h * y0[n + k] + h01 * y1[k] + h11 * h * y1[n + k];
+        }
+        Some(y)
+    }
+
+    /// Frames exactly `1 / fps` apart from the first recorded time (via `state_at`), up to the
+    /// last one at or before the end of the recorded span. Events are kept as they are.
+    pub fn resample(self, fps: f64) -> Trajectory {
+        let (Some(&t_start), Some(&t_end)) = (self.t_axis.first(), self.t_axis.last()) else {
+            return self;
+        };
+        let span = t_end - t_start;
+        // A hair of slack so a frame due exactly at the end survives rounding in span · fps
+        let count = (span.abs() * fps * (1.0 + 1e-12)).floor() as usize + 1;
+        let step = span.signum() / fps;
+        let t_axis: Vec<f64> = (0..count).map(|k| t_start + k as f64 * step).collect();
+        let sol = t_axis
+            .iter()
+            .map(|&t| self.state_at(t).expect("resampled times lie within the recorded span"))
+            .collect();
+        Trajectory { t_axis, sol, ..self }
+    }
+}
+
+/// Integrates the chain with RK4. Generic over the scalar type: `f64` by default, `f32`
+/// for low-precision previews (see `Precision`). The analysis helpers are `f64`-only.
+/// Serializes its full configuration (see `Checkpoint`); the scratch model is rebuilt.
+#[derive(Deserialize, Serialize)]
+pub struct NPendulumSolver<T = f64> {
     pub n: usize,
-    pub masses: Vec<f64>,
-    pub lengths: Vec<f64>,
+    pub masses: Vec<T>,
+    pub lengths: Vec<T>,
+    pub g: T,       // Gravity magnitude
+    pub g_angle: T, // Gravity direction from straight down (see `NPendulumMath::g_angle`)
+    pub rod_inertia: bool, // Uniform rods instead of point masses (see `NPendulumMath`)
+    pub gravity_enabled: bool,
+    pub gravity_model: GravityModel<T>, // See `NPendulumMath::gravity_model`
+    #[serde(default)]
+    pub centripetal_form: CentripetalForm, // See `NPendulumMath::centripetal_form`
+    pub parents: Option<Vec<usize>>, // Tree topology (see `NPendulumMath::parents`); None = chain
+    /// [false, f1, ..., fn]: links with f_i set hold their angle (α_i = 0, θ_i frozen) while
+    /// still carrying the links below them. `None` = every link free.
+    pub fixed: Option<Vec<bool>>,
+    /// Joint friction; `None` = conservative.
+    pub damping: Option<Damping<T>>,
+    /// Math model reused by `deriv` across steps, so the hot loop does not rebuild (and
+    /// reallocate) the 1-based vectors on every stage. Refreshed from the fields above on
+    /// every call, so it never goes stale.
+    #[serde(skip)]
+    scratch: RefCell<Option<NPendulumMath<T>>>,
 }
 
-impl NPendulumSolver {
-    pub fn new(n: usize, masses: Vec<f64>, lengths: Vec<f64>) -> Self {
-        Self { n, masses, lengths }
+impl<T: RealField + Copy> NPendulumSolver<T> {
+    pub fn new(n: usize, masses: Vec<T>, lengths: Vec<T>) -> Self {
+        Self {
+            n,
+            masses,
+            lengths,
+            g: nalgebra::convert(9.81),
+            g_angle: T::zero(),
+            rod_inertia: false,
+            gravity_enabled: true,
+            gravity_model: GravityModel::Uniform,
+            centripetal_form: CentripetalForm::Direct,
+            parents: None,
+            fixed: None,
+            damping: None,
+            scratch: RefCell::new(None),
+        }
+    }
+
+    /// Sets gravity from a 2D vector; (0, −9.81) is the default straight-down field.
+    pub fn with_gravity(mut self, gx: T, gy: T) -> Self {
+        self.g = gx.hypot(gy);
+        self.g_angle = gx.atan2(-gy);
+        self
+    }
+
+    /// Makes gravity position-dependent (see `GravityModel`); `Uniform` keeps `with_gravity`'s
+    /// constant field.
+    pub fn with_gravity_model(mut self, model: GravityModel<T>) -> Self {
+        self.gravity_model = model;
+        self
+    }
+
+    /// Evaluates C with the given formula (see `CentripetalForm`); the default is `Direct`.
+    pub fn with_centripetal_form(mut self, form: CentripetalForm) -> Self {
+        self.centripetal_form = form;
+        self
     }
 
-    /// Computes α = M⁻¹ (-C - G)
-    pub fn accelerations(&self, angles: &[f64], ang_vels: &[f64]) -> DVector<f64> {
-        let math = NPendulumMath::new(
+    /// Treats each link as a uniform rod of mass m_i (CM at its midpoint) instead of a
+    /// massless rod carrying a point mass at its end.
+    pub fn with_rod_inertia(mut self, rod_inertia: bool) -> Self {
+        self.rod_inertia = rod_inertia;
+        self
+    }
+
+    /// Switches gravity off entirely (G = 0) for pure-inertia runs; the gravity vector set by
+    /// `with_gravity` is kept but ignored.
+    pub fn with_gravity_enabled(mut self, enabled: bool) -> Self {
+        self.gravity_enabled = enabled;
+        self
+    }
+
+    /// Hangs the links as a tree: `parents` is [0, p1, ..., pn] with p_i < i the link that
+    /// link i hangs from (0 = pivot). `None` keeps the serial chain.
+    pub fn with_topology(mut self, parents: Option<Vec<usize>>) -> Self {
+        self.parents = parents;
+        self
+    }
+
+    /// Pins the links flagged in `fixed` ([false, f1, ..., fn]) at their current angle.
+    pub fn with_fixed_links(mut self, fixed: Option<Vec<bool>>) -> Self {
+        self.fixed = fixed;
+        self
+    }
+
+    /// Applies joint friction (see `DampingModel`).
+    pub fn with_damping(mut self, damping: Option<Damping<T>>) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    /// Link that link `k` hangs from (0 = pivot).
+    fn parent(&self, k: usize) -> usize {
+        self.parents.as_ref().map_or(k - 1, |parents| parents[k])
+    }
+
+    /// Builds the 1-indexed math model for the given angles and angular velocities.
+    fn math_with(&self, angles: Vec<T>, ang_vels: Vec<T>) -> NPendulumMath<T> {
+        let mut math = NPendulumMath::new(
             self.n,
             self.masses.clone(), // Still technically a clone, but math.rs can be updated to borrow
             self.lengths.clone(),
-            angles.to_vec(),
-            ang_vels.to_vec(),
+            angles,
+            ang_vels,
         );
+        math.g = self.g;
+        math.g_angle = self.g_angle;
+        math.rod_inertia = self.rod_inertia;
+        math.gravity_enabled = self.gravity_enabled;
+        math.gravity_model = self.gravity_model;
+        math.centripetal_form = self.centripetal_form;
+        math.parents = self.parents.clone();
+        math
+    }
+
+    /// Cartesian (x, y) of each bob for a state vector, pivot at the origin.
+    /// Same convention as the frontend positions: x = Σ L sin θ, y = -Σ L cos θ
+    /// (summed along the path from the pivot).
+    pub fn bob_positions(&self, y: &DVector<T>) -> Vec<(T, T)> {
+        let mut bobs: Vec<(T, T)> = Vec::with_capacity(self.n);
+        for k in 1..=self.n {
+            let (x, z) = match self.parent(k) {
+                0 => (T::zero(), T::zero()),
+                p => bobs[p - 1],
+            };
+            bobs.push((x + self.lengths[k] * y[k - 1].sin(), z - self.lengths[k] * y[k - 1].cos()));
+        }
+        bobs
+    }
+
+    /// Cartesian velocity (ẋ, ẏ) of each bob for a state vector, the time derivative of
+    /// `bob_positions`: ẋ = Σ L ω cos θ, ẏ = Σ L ω sin θ along the path from the pivot.
+    pub fn bob_velocities(&self, y: &DVector<T>) -> Vec<(T, T)> {
+        let n = self.n;
+        let mut vels: Vec<(T, T)> = Vec::with_capacity(n);
+        for k in 1..=n {
+            let (vx, vy) = match self.parent(k) {
+                0 => (T::zero(), T::zero()),
+                p => vels[p - 1],
+            };
+            let (sin, cos) = y[k - 1].sin_cos();
+            let w = self.lengths[k] * y[n + k - 1];
+            vels.push((vx + w * cos, vy + w * sin));
+        }
+        vels
+    }
+
+    /// True if any two bobs not joined by a rod are closer than `min_separation`.
+    /// (A bob and its parent's bob are always exactly one rod length apart.)
+    fn bobs_in_contact(&self, y: &DVector<T>, min_separation: T) -> bool {
+        let bobs = self.bob_positions(y);
+        let min_sq = min_separation * min_separation;
+        (0..bobs.len()).any(|i| {
+            (i + 1..bobs.len()).filter(|&j| self.parent(j + 1) != i + 1).any(|j| {
+                let (dx, dy) = (bobs[i].0 - bobs[j].0, bobs[i].1 - bobs[j].1);
+                dx * dx + dy * dy < min_sq
+            })
+        })
+    }
+
+    /// Builds the 1-indexed math model for a state vector [θ1...θn, ω1...ωn].
+    fn math_at(&self, y: &DVector<T>) -> NPendulumMath<T> {
+        let n = self.n;
+        let mut angles = vec![T::zero(); n + 1];
+        let mut ang_vels = vec![T::zero(); n + 1];
+        angles[1..=n].copy_from_slice(y.rows(0, n).as_slice());
+        ang_vels[1..=n].copy_from_slice(y.rows(n, n).as_slice());
 
-        let m_mat = math.set_mass_matrix();
+        self.math_with(angles, ang_vels)
+    }
+
+    /// Total angular momentum about the pivot for a state vector.
+    pub fn angular_momentum(&self, y: &DVector<T>) -> T {
+        self.math_at(y).angular_momentum()
+    }
+
+    /// Total mechanical energy (kinetic + potential) for a state vector.
+    pub fn total_energy(&self, y: &DVector<T>) -> T {
+        self.math_at(y).total_energy()
+    }
+
+    /// Power delivered by gravity, Σ m_i g · v_i over the mass points. G = ∂V/∂θ, so this is
+    /// −G · ω, which also covers rods, tilted gravity and the inverse-square field.
+    pub fn gravity_power(&self, y: &DVector<T>) -> T {
+        let n = self.n;
+        -self.math_at(y).set_grav_matrix().dot(&y.rows(n, n))
+    }
+
+    /// 2-norm condition number σ_max / σ_min of the mass matrix for a state vector. Large
+    /// values mark configurations where solving for the accelerations loses precision.
+    pub fn mass_matrix_condition(&self, y: &DVector<T>) -> T {
+        let singular = self.math_at(y).set_mass_matrix().singular_values();
+        singular.max() / singular.min()
+    }
+
+    /// Power delivered by the friction torques, Σ Q_i ω_i (never positive; zero without
+    /// `damping`). Fixed links are at rest, so they contribute nothing.
+    pub fn damping_power(&self, y: &DVector<T>) -> T {
+        let n = self.n;
+        self.damping.as_ref().map_or(T::zero(), |damping| {
+            (1..=n).fold(T::zero(), |acc, i| acc + damping.torque(i, y[n + i - 1]) * y[n + i - 1])
+        })
+    }
+
+    /// Lagrangian L = T − V and Hamiltonian H = T + V for a state vector. With time-independent
+    /// constraints H is the total energy, so without damping it stays constant while L swings
+    /// with the exchange between T and V.
+    pub fn lagrangian_and_hamiltonian(&self, y: &DVector<T>) -> (T, T) {
+        let math = self.math_at(y);
+        let (kinetic, potential) = (math.kinetic_energy(), math.potential_energy());
+        (kinetic - potential, kinetic + potential)
+    }
+
+    /// Scales ω in place so the total energy equals `target`: T(sω) = s² T(ω), so
+    /// s = √((target − V) / T). Left untouched when there is no kinetic energy to scale
+    /// or the target lies below the potential energy of the current angles.
+    fn project_energy(&self, y: &mut DVector<T>, target: T) {
+        let math = self.math_at(y);
+        let kinetic = math.kinetic_energy();
+        let available = target - math.potential_energy();
+        if kinetic > T::zero() && available > T::zero() {
+            let n = self.n;
+            y.rows_mut(n, n).scale_mut((available / kinetic).sqrt());
+        }
+    }
+
+    /// Axial force in each link for a state vector, [T1, ..., Tn] (positive = tension).
+    pub fn tensions(&self, y: &DVector<T>) -> Result<Vec<T>, SimError> {
+        let math = self.math_at(y);
+        let alpha =
+            Self::accelerations(T::zero(), &math, self.fixed.as_deref(), self.damping.as_ref())?;
+        let mut alphas = vec![T::zero(); self.n + 1];
+        alphas[1..].copy_from_slice(alpha.as_slice());
+        Ok(math.tensions(&alphas)[1..].to_vec())
+    }
+
+    /// Computes α = M⁻¹ (Q - C - G) at time `t` for a prepared math model
+    /// (the current system is autonomous; `t` is the hook for time-dependent forcing).
+    /// Q is the friction torque from `damping` (zero without it).
+    /// Links flagged in `fixed` get their row replaced by α_i = 0, so the free links solve
+    /// M_ff α_f = (Q − C − G)_f with the fixed links still in their coupling terms.
+    /// Fails with `SimError::SingularMatrix` if the mass matrix cannot be inverted.
+    fn accelerations(
+        _t: T,
+        math: &NPendulumMath<T>,
+        fixed: Option<&[bool]>,
+        damping: Option<&Damping<T>>,
+    ) -> Result<DVector<T>, SimError> {
+        let mut m_mat = math.set_mass_matrix();
         let c_vec = math.set_centripetal_matrix();
         let g_vec = math.set_grav_matrix();
 
-        // RHS = -(C + G)
-        let rhs = -(c_vec + g_vec);
+        // RHS = Q - (C + G), built in place in C's buffer
+        let mut rhs = c_vec;
+        rhs += g_vec;
+        rhs.neg_mut();
+        if let Some(damping) = damping {
+            for i in 1..=math.n {
+                rhs[i - 1] += damping.torque(i, math.ang_vels[i]);
+            }
+        }
+
+        if let Some(fixed) = fixed {
+            for i in (1..=math.n).filter(|&i| fixed[i]) {
+                m_mat.row_mut(i - 1).fill(T::zero());
+                m_mat[(i - 1, i - 1)] = T::one();
+                rhs[i - 1] = T::zero();
+            }
+        }
 
         // nalgebra's LU decomposition solver (efficient for n < 100)
-        m_mat.lu().solve(&rhs).expect("Linear system is singular")
+        if m_mat.lu().solve_mut(&mut rhs) {
+            Ok(rhs)
+        } else {
+            Err(SimError::SingularMatrix)
+        }
     }
 
-    /// Computes dy/dt = [ω, α]
-    pub fn deriv(&self, y: &DVector<f64>) -> DVector<f64> {
+    /// Computes dy/dt = [ω, α] at time `t`, loading the state into the scratch model.
+    pub fn deriv(&self, t: T, y: &DVector<T>) -> Result<DVector<T>, SimError> {
         let n = self.n;
-        
-        // Prepare 1-indexed vectors for math logic
-        let mut angles = vec![0.0; n + 1];
-        let mut ang_vels = vec![0.0; n + 1];
-        
-        // Use slice copies to avoid manual loops
-        angles[1..=n].copy_from_slice(y.rows(0, n).as_slice());
-        ang_vels[1..=n].copy_from_slice(y.rows(n, n).as_slice());
 
-        let alpha = self.accelerations(&angles, &ang_vels);
+        let alpha = {
+            let mut scratch = self.scratch.borrow_mut();
+            let math = scratch.get_or_insert_with(|| self.math_at(y));
+
+            // Refresh parameters and state in place (slice copies, no allocation)
+            math.masses.copy_from_slice(&self.masses);
+            math.lengths.copy_from_slice(&self.lengths);
+            math.g = self.g;
+            math.g_angle = self.g_angle;
+            math.rod_inertia = self.rod_inertia;
+            math.gravity_enabled = self.gravity_enabled;
+            math.gravity_model = self.gravity_model;
+            math.centripetal_form = self.centripetal_form;
+            if math.parents != self.parents {
+                math.parents.clone_from(&self.parents);
+            }
+            math.angles[1..=n].copy_from_slice(y.rows(0, n).as_slice());
+            math.ang_vels[1..=n].copy_from_slice(y.rows(n, n).as_slice());
+
+            Self::accelerations(t, math, self.fixed.as_deref(), self.damping.as_ref())?
+        };
 
         let mut dydt = DVector::zeros(2 * n);
         
@@ -54,47 +607,1285 @@ impl NPendulumSolver {
         // dω/dt = α
         dydt.rows_mut(n, n).copy_from(&alpha);
         
-        dydt
+        Ok(dydt)
+    }
+
+    /// Standard RK4 Step with reduced allocations, from time `t` to `t + dt`
+    fn rk4_step(&self, t: T, y: &DVector<T>, dt: T) -> Result<DVector<T>, SimError> {
+        rk4(|t, y| self.deriv(t, y), t, y, dt)
+    }
+
+    /// Packs 1-based angles and angular velocities into a state vector [θ1...θn, ω1...ωn].
+    /// Fails with `LengthMismatch` unless both hold exactly n values after the padding.
+    pub fn initial_state(&self, angles: &[T], ang_vels: &[T]) -> Result<DVector<T>, SimError> {
+        let n = self.n;
+        check_one_based("initial_angles", angles, n)?;
+        check_one_based("initial_ang_vels", ang_vels, n)?;
+        let mut y = DVector::zeros(2 * n);
+        y.rows_mut(0, n).copy_from_slice(&angles[1..=n]);
+        y.rows_mut(n, n).copy_from_slice(&ang_vels[1..=n]);
+        Ok(y)
+    }
+
+    /// Integrates from a full state vector [θ1...θn, ω1...ωn] (see `initial_state`, or the
+    /// final state of a previous run). Same as `solve_from_state_with_progress`, unobserved.
+    pub fn solve_from_state(
+        &self,
+        y: DVector<T>,
+        t_max: T,
+        n_points: usize,
+        opts: &SolveOptions<T>,
+    ) -> Result<Trajectory<T>, SimError> {
+        self.solve_from_state_with_progress(y, t_max, n_points, opts, |_| {})
     }
 
-    /// Standard RK4 Step with reduced allocations
-    fn rk4_step(&self, y: &DVector<f64>, dt: f64) -> DVector<f64> {
-        let k1 = self.deriv(y);
-        let k2 = self.deriv(&(y + &k1 * (dt * 0.5)));
-        let k3 = self.deriv(&(y + &k2 * (dt * 0.5)));
-        let k4 = self.deriv(&(y + &k3 * dt));
+    /// Same as `solve_from_state_observed`, without a per-step observer.
+    pub fn solve_from_state_with_progress(
+        &self,
+        y: DVector<T>,
+        t_max: T,
+        n_points: usize,
+        opts: &SolveOptions<T>,
+        on_progress: impl FnMut(f64),
+    ) -> Result<Trajectory<T>, SimError> {
+        let on_frame = |_, _, _: &_| ControlFlow::Continue(());
+        self.solve_from_state_observed(y, t_max, n_points, opts, on_progress, |_| {}, on_frame)
+    }
 
-        y + (k1 + k2 * 2.0 + k3 * 2.0 + k4) * (dt / 6.0)
+    /// Same as `solve_from_state_observed` with only the per-frame callback: it sees the step
+    /// index, time and state of every recorded frame, and `Break` ends the run there with the
+    /// frames so far (e.g. for progress UIs or caller-side stopping rules).
+    pub fn solve_from_state_until(
+        &self,
+        y: DVector<T>,
+        t_max: T,
+        n_points: usize,
+        opts: &SolveOptions<T>,
+        on_frame: impl FnMut(usize, T, &DVector<T>) -> ControlFlow<()>,
+    ) -> Result<Trajectory<T>, SimError> {
+        self.solve_from_state_observed(y, t_max, n_points, opts, |_| {}, |_| {}, on_frame)
     }
 
     /// Main integration loop
-    pub fn solve(
+    /// Integrates over `n_points` steps but only records every `stride`-th state
+    /// (the final state is always recorded). With a `CollisionCheck` configured, every step
+    /// is checked for bob contact; in stop mode the contact frame is recorded and the run ends.
+    /// `on_progress` is called after every step with the fraction of steps completed so far;
+    /// `on_step` sees the state at every step, recorded or not. `on_frame` is called with the
+    /// step index, time and state of each recorded frame; returning `Break` stops the run
+    /// there and returns the frames recorded so far.
+    /// A negative `t_max` integrates backward in time: the steps are negative and `t_axis`
+    /// decreases from 0 to `t_max`. With `opts.warmup`, everything is shifted to start there.
+    #[allow(clippy::too_many_arguments)] // One hook per kind of observer; wrappers pass no-ops
+    pub fn solve_from_state_observed(
         &self,
-        initial_angles: Vec<f64>,
-        initial_ang_vels: Vec<f64>,
+        mut y: DVector<T>,
+        t_max: T,
+        n_points: usize,
+        opts: &SolveOptions<T>,
+        mut on_progress: impl FnMut(f64),
+        mut on_step: impl FnMut(&DVector<T>),
+        mut on_frame: impl FnMut(usize, T, &DVector<T>) -> ControlFlow<()>,
+    ) -> Result<Trajectory<T>, SimError> {
+        let stride = opts.stride;
+        let dt = t_max / nalgebra::convert((n_points - 1) as f64);
+        let h = dt / nalgebra::convert(opts.substeps as f64);
+        let n_frames = (n_points - 1).div_ceil(stride) + 1;
+        
+        let mut t_axis = Vec::with_capacity(n_frames);
+        let mut sol = Vec::with_capacity(n_frames);
+
+        // Fixed links never move, whatever velocity the state came with
+        if let Some(fixed) = &self.fixed {
+            for i in (1..=self.n).filter(|&i| fixed[i]) {
+                y[self.n + i - 1] = T::zero();
+            }
+        }
+
+        let mut first_collision_time = None;
+        let mut blew_up_at_time = None;
+        let initial_energy = opts.energy_correction.then(|| self.total_energy(&y));
+        let mut curr_t = T::zero();
+
+        // Burn-in at (at most) the recording step size, shortened to land on `warmup`. A blow-up
+        // ends it early and is then reported by the first step of the recorded run.
+        if let Some(warmup) = opts.warmup.filter(|&w| w > T::zero()) {
+            let steps = nalgebra::try_convert::<T, f64>((warmup / dt.abs()).ceil())
+                .map_or(1, |steps| (steps as usize).max(1));
+            let h_warm = warmup / nalgebra::convert((steps * opts.substeps) as f64);
+            for step in 0..steps {
+                check_deadline(opts.deadline, step)?;
+                if blew_up(&y, self.n..2 * self.n, opts.max_ang_vel) {
+                    break;
+                }
+                for _ in 0..opts.substeps {
+                    y = self.rk4_step(curr_t, &y, h_warm)?;
+                    curr_t += h_warm;
+                }
+                if let Some(energy) = initial_energy {
+                    self.project_energy(&mut y, energy);
+                }
+            }
+        }
+
+        for step in 0..n_points {
+            check_deadline(opts.deadline, step)?;
+            if blew_up(&y, self.n..2 * self.n, opts.max_ang_vel) {
+                blew_up_at_time = Some(curr_t);
+                break;
+            }
+            let mut record = step % stride == 0 || step == n_points - 1;
+            let mut stop = false;
+
+            if let Some(check) = &opts.collision {
+                let fresh = first_collision_time.is_none();
+                if fresh && self.bobs_in_contact(&y, check.min_separation) {
+                    first_collision_time = Some(curr_t);
+                    record |= check.stop;
+                    stop = check.stop;
+                }
+            }
+
+            on_step(&y);
+            if record {
+                t_axis.push(curr_t);
+                sol.push(y.clone());
+                stop |= on_frame(step, curr_t, &y).is_break();
+            }
+            if stop {
+                break;
+            }
+            
+            for _ in 0..opts.substeps {
+                y = self.rk4_step(curr_t, &y, h)?;
+                curr_t += h;
+            }
+            if let Some(energy) = initial_energy {
+                self.project_energy(&mut y, energy);
+            }
+            on_progress((step + 1) as f64 / n_points as f64);
+        }
+
+        Ok(Trajectory { t_axis, sol, first_collision_time, blew_up_at_time })
+    }
+}
+
+/// Analysis helpers built on the integrator (double precision only).
+impl NPendulumSolver {
+    /// Estimates the largest Lyapunov exponent with the two-trajectory (Benettin) method.
+    /// A companion trajectory starts `epsilon` away in θ1; after every step the separation
+    /// is measured, its log growth accumulated, and the companion rescaled back to `epsilon`
+    /// along the separation vector. Steps, substeps, stride and deadline follow `opts` like the
+    /// main run, which it also follows in stopping at a blow-up or a stopping collision (its
+    /// warmup and energy correction are not applied). Returns the running estimate at every
+    /// recorded frame and the final estimate.
+    pub fn lyapunov_exponent(
+        &self,
+        y0: &DVector<f64>,
         t_max: f64,
         n_points: usize,
-    ) -> (Vec<f64>, Vec<DVector<f64>>) {
+        epsilon: f64,
+        opts: &SolveOptions<f64>,
+    ) -> Result<(Vec<f64>, f64), SimError> {
         let n = self.n;
+        let stride = opts.stride;
         let dt = t_max / (n_points - 1) as f64;
-        
-        let mut t_axis = Vec::with_capacity(n_points);
-        let mut sol = Vec::with_capacity(n_points);
+        let h = dt / opts.substeps as f64;
+        let mut y = y0.clone();
+        let mut y_pert = y0.clone();
+        y_pert[0] += epsilon;
 
-        // Initialize state vector [θ1...θn, ω1...ωn]
-        let mut y = DVector::zeros(2 * n);
-        y.rows_mut(0, n).copy_from_slice(&initial_angles[1..=n]);
-        y.rows_mut(n, n).copy_from_slice(&initial_ang_vels[1..=n]);
+        let mut log_sum = 0.0;
+        let mut estimate = 0.0;
+        let mut running = Vec::with_capacity((n_points - 1).div_ceil(stride) + 1);
 
         let mut curr_t = 0.0;
-        for _ in 0..n_points {
-            t_axis.push(curr_t);
-            sol.push(y.clone());
-            
-            y = self.rk4_step(&y, dt);
-            curr_t += dt;
+        for step in 0..n_points {
+            check_deadline(opts.deadline, step)?;
+            if blew_up(&y, n..2 * n, opts.max_ang_vel) {
+                break;
+            }
+            let stop = opts
+                .collision
+                .as_ref()
+                .is_some_and(|c| c.stop && self.bobs_in_contact(&y, c.min_separation));
+            if step % stride == 0 || step == n_points - 1 || stop {
+                running.push(estimate);
+            }
+            if stop {
+                break;
+            }
+
+            for _ in 0..opts.substeps {
+                y = self.rk4_step(curr_t, &y, h)?;
+                y_pert = self.rk4_step(curr_t, &y_pert, h)?;
+                curr_t += h;
+            }
+
+            // Renormalize the companion back to distance ε along the separation
+            let separation = &y_pert - &y;
+            let d = separation.norm();
+            log_sum += (d / epsilon).ln();
+            y_pert = &y + separation * (epsilon / d);
+
+            estimate = log_sum / ((step + 1) as f64 * dt);
+        }
+
+        Ok((running, estimate))
+    }
+
+    /// Measures the oscillation period of θ1 from a solved trajectory: the time until θ1
+    /// returns to its initial value moving in the same direction. When starting from rest
+    /// (ω1 = 0) the equivalent event is ω1 returning to zero with the same sign of α1.
+    /// The crossing is bracketed by the recorded frames, then pinned down with Newton-Raphson
+    /// on a partial RK4 step from the frame before it. Returns `None` if no cycle completes.
+    pub fn measure_period(&self, traj: &Trajectory) -> Result<Option<f64>, SimError> {
+        const MAX_ITER: usize = 20;
+        let n = self.n;
+        let y0 = &traj.sol[0];
+
+        // Event function and its time derivative: (θ1 − θ1(0), ω1) or (ω1, α1)
+        let from_rest = y0[n].abs() < 1e-12;
+        let event = |t: f64, y: &DVector<f64>| -> Result<(f64, f64), SimError> {
+            if from_rest {
+                Ok((y[n], self.deriv(t, y)?[n]))
+            } else {
+                Ok((y[0] - y0[0], y[n]))
+            }
+        };
+        let direction = event(traj.t_axis[0], y0)?.1.signum();
+        if direction == 0.0 {
+            return Ok(None); // At rest in equilibrium: nothing oscillates
+        }
+
+        for k in 1..traj.sol.len() {
+            let (g_prev, _) = event(traj.t_axis[k - 1], &traj.sol[k - 1])?;
+            let (g_curr, _) = event(traj.t_axis[k], &traj.sol[k])?;
+            if !(g_prev * direction < 0.0 && g_curr * direction >= 0.0) {
+                continue;
+            }
+
+            // Newton-Raphson on the step length τ from the bracketing frame
+            let (t_lo, y_lo) = (traj.t_axis[k - 1], &traj.sol[k - 1]);
+            let h = traj.t_axis[k] - t_lo;
+            let mut tau = h * g_prev / (g_prev - g_curr);
+            for _ in 0..MAX_ITER {
+                let (g, dg) = event(t_lo + tau, &self.rk4_step(t_lo, y_lo, tau)?)?;
+                let next = (tau - g / dg).clamp(0.0, h);
+                let converged = (next - tau).abs() < 1e-13;
+                tau = next;
+                if converged {
+                    break;
+                }
+            }
+            return Ok(Some(t_lo + tau - traj.t_axis[0]));
+        }
+        Ok(None)
+    }
+
+    /// System Jacobian ∂(dy/dt)/∂y (2n x 2n) at state `y` and time `t`, by central finite
+    /// differences of `deriv`. Column j is the response to perturbing y_j by ±h, with h scaled
+    /// to the magnitude of y_j. Its eigenvalues give the local (linear) stability: purely
+    /// imaginary pairs at a stable equilibrium, a positive real part at an unstable one.
+    pub fn jacobian(&self, t: f64, y: &DVector<f64>) -> Result<DMatrix<f64>, SimError> {
+        const H: f64 = 1e-6;
+
+        let dim = 2 * self.n;
+        let mut jac = DMatrix::zeros(dim, dim);
+        for j in 0..dim {
+            let h = H * y[j].abs().max(1.0);
+            let mut fwd = y.clone();
+            let mut back = y.clone();
+            fwd[j] += h;
+            back[j] -= h;
+            let col = (self.deriv(t, &fwd)? - self.deriv(t, &back)?) / (2.0 * h);
+            jac.set_column(j, &col);
+        }
+        Ok(jac)
+    }
+
+    /// Returns every static equilibrium of the chain: each link hanging along gravity (θ=φ)
+    /// or pointing against it (θ=φ+π). Each entry is `(angles, stable)`; only the all-hanging
+    /// configuration is stable.
+    pub fn fixed_points(&self) -> Vec<(DVector<f64>, bool)> {
+        (0..1usize << self.n)
+            .map(|bits| {
+                let angles = DVector::from_fn(self.n, |i, _| {
+                    let flip = if bits & (1 << i) != 0 { std::f64::consts::PI } else { 0.0 };
+                    self.g_angle + flip
+                });
+                (angles, bits == 0)
+            })
+            .collect()
+    }
+
+    /// Refines a near-equilibrium guess (angles θ1..θn, at rest) with Newton iteration on
+    /// α(θ, ω=0) = 0. The Jacobian is approximated by central finite differences of `deriv`.
+    pub fn refine_equilibrium(&self, guess: &[f64]) -> Result<DVector<f64>, SimError> {
+        const MAX_ITER: usize = 50;
+        const TOL: f64 = 1e-12;
+        const H: f64 = 1e-6;
+
+        let n = self.n;
+        // α at rest for the given angles (lower half of dy/dt)
+        let residual = |theta: &DVector<f64>| -> Result<DVector<f64>, SimError> {
+            let mut y = DVector::zeros(2 * n);
+            y.rows_mut(0, n).copy_from(theta);
+            Ok(self.deriv(0.0, &y)?.rows(n, n).into_owned())
+        };
+
+        let mut theta = DVector::from_column_slice(guess);
+        for _ in 0..MAX_ITER {
+            let f = residual(&theta)?;
+            if f.norm() < TOL {
+                return Ok(theta);
+            }
+
+            let mut jac = DMatrix::zeros(n, n);
+            for j in 0..n {
+                let mut fwd = theta.clone();
+                let mut back = theta.clone();
+                fwd[j] += H;
+                back[j] -= H;
+                let col = (residual(&fwd)? - residual(&back)?) / (2.0 * H);
+                jac.set_column(j, &col);
+            }
+
+            let delta = jac.lu().solve(&f).ok_or(SimError::SingularMatrix)?;
+            theta -= delta;
+        }
+
+        if residual(&theta)?.norm() < TOL {
+            Ok(theta)
+        } else {
+            Err(SimError::NotConverged { iterations: MAX_ITER })
         }
+    }
+
+    /// Time at which `link` (1-based) first flips over, i.e. its cumulative angle θ_link passes
+    /// an odd multiple of π (±π from a start within one turn), integrating from `y0` in fixed
+    /// steps of `dt` up to `t_max` (the crossing is interpolated within its step). `None` if it
+    /// does not flip in time, or cannot: in uniform gravity V is a sum of independent per-link
+    /// terms, so no state with less energy than "this link at π, every other one hanging" can
+    /// ever get there. Other gravity models couple the links' potentials and always integrate.
+    pub fn flip_time(
+        &self,
+        y0: DVector<f64>,
+        link: usize,
+        t_max: f64,
+        dt: f64,
+        deadline: Option<Deadline>,
+    ) -> Result<Option<f64>, SimError> {
+        let pi = std::f64::consts::PI;
+        let k = link - 1;
+
+        if self.gravity_model == GravityModel::Uniform {
+            let mut barrier = DVector::zeros(2 * self.n);
+            barrier.rows_mut(0, self.n).fill(self.g_angle);
+            barrier[k] = pi;
+            if self.total_energy(&y0) < self.total_energy(&barrier) {
+                return Ok(None);
+            }
+        }
+
+        // Index j of the turn [(2j − 1)π, (2j + 1)π) holding θ: it changes exactly when θ
+        // crosses an odd multiple of π
+        let turn = |theta: f64| ((theta + pi) / (2.0 * pi)).floor();
+        let steps = (t_max / dt).ceil() as usize;
+        let mut y = y0;
+        for step in 0..steps {
+            check_deadline(deadline, step)?;
+            let t = step as f64 * dt;
+            let next = self.rk4_step(t, &y, dt)?;
+            let (from, to) = (turn(y[k]), turn(next[k]));
+            if from != to {
+                // Rising crosses into turn `to` at its lower edge, falling out of `from` at its
+                let crossed = (2.0 * from.max(to) - 1.0) * pi;
+                let frac = (crossed - y[k]) / (next[k] - y[k]);
+                return Ok(Some(t + frac * dt));
+            }
+            y = next;
+        }
+        Ok(None)
+    }
+
+    /// Draws a state on the energy shell E = `energy` for Monte Carlo runs: angles uniform in
+    /// [-π, π), redrawn until V(θ) < E, and a uniform random ω direction scaled onto the
+    /// shell (see `project_energy`). Pinned links get ω = 0. The same seed gives the same state.
+    pub fn sample_at_energy(&self, energy: f64, seed: u64) -> Result<DVector<f64>, SimError> {
+        const MAX_TRIES: usize = 10_000;
+
+        let n = self.n;
+        let pi = std::f64::consts::PI;
+        let mut rng = SplitMix64(seed);
+        for _ in 0..MAX_TRIES {
+            let mut y = DVector::from_fn(2 * n, |i, _| {
+                if i < n { rng.uniform(-pi, pi) } else { rng.uniform(-1.0, 1.0) }
+            });
+            if let Some(fixed) = &self.fixed {
+                for k in (1..=n).filter(|&k| fixed[k]) {
+                    y[n + k - 1] = 0.0;
+                }
+            }
+            let math = self.math_at(&y);
+            if math.potential_energy() < energy && math.kinetic_energy() > 0.0 {
+                self.project_energy(&mut y, energy);
+                return Ok(y);
+            }
+        }
+        Err(SimError::NotConverged { iterations: MAX_TRIES })
+    }
+
+    /// Small-oscillation normal-mode frequencies (rad/s, ascending) about the hanging
+    /// equilibrium (see `NPendulumMath::normal_mode_frequencies`).
+    pub fn normal_mode_frequencies(&self) -> Result<Vec<f64>, SimError> {
+        let zeros = vec![0.0; self.n + 1];
+        self.math_with(zeros.clone(), zeros)
+            .normal_mode_frequencies()
+            .ok_or(SimError::SingularMatrix)
+    }
+
+    /// State at rest displaced along normal mode `index` (0 = lowest frequency): every θ_i is
+    /// the rest angle plus `amplitude` (radians) times the mode's shape, whose largest angle
+    /// is 1 (see `NPendulumMath::normal_modes`). Small amplitudes then oscillate at that
+    /// mode's frequency alone.
+    pub fn normal_mode_state(
+        &self,
+        index: usize,
+        amplitude: f64,
+    ) -> Result<DVector<f64>, SimError> {
+        if index >= self.n {
+            let reason = format!("must be below the number of links ({})", self.n);
+            return Err(SimError::InvalidParam { field: "normal_mode", reason });
+        }
+        let zeros = vec![0.0; self.n + 1];
+        let modes = self.math_with(zeros.clone(), zeros).normal_modes();
+        let (_, shape) = modes.ok_or(SimError::SingularMatrix)?.swap_remove(index);
+        let mut y = DVector::zeros(2 * self.n);
+        y.rows_mut(0, self.n).copy_from(&shape.map(|v| self.g_angle + amplitude * v));
+        Ok(y)
+    }
+
+    /// Closed-form motion of a single free, undamped link in uniform gravity started from
+    /// `y0` = [θ, ω] (see `Libration`). `None` for any other chain, without gravity, or when
+    /// the link has enough energy to swing over the top.
+    fn libration(&self, y0: &DVector<f64>) -> Option<Libration> {
+        let undamped = self.damping.as_ref().is_none_or(|d| d.model == DampingModel::None);
+        let free = self.fixed.as_ref().is_none_or(|fixed| !fixed[1]);
+        let uniform = self.gravity_model == GravityModel::Uniform;
+        if self.n != 1 || !undamped || !free || !uniform {
+            return None;
+        }
+        let omega0 = *self.normal_mode_frequencies().ok()?.first()?;
+        if omega0 <= 0.0 {
+            return None;
+        }
+
+        // Swing φ about the rest angle, wrapped into (−π, π]
+        let pi = std::f64::consts::PI;
+        let phi = pi - (pi - (y0[0] - self.g_angle)).rem_euclid(2.0 * pi);
+        let s = (0.5 * phi).sin();
+        let m = s * s + (y0[1] / (2.0 * omega0)).powi(2);
+        if m >= 1.0 {
+            return None;
+        }
+        let k = m.sqrt();
+        let quarter = elliptic_k(m);
+        let u0 = if k > 0.0 {
+            let f = elliptic_f((s / k).clamp(-1.0, 1.0).asin(), m);
+            if y0[1] >= 0.0 { f } else { 2.0 * quarter - f }
+        } else {
+            0.0
+        };
+        Some(Libration { center: y0[0] - phi, omega0, k, quarter, u0 })
+    }
+
+    /// Exact period of a single pendulum started from `y0`, T = 4 K(k²) / ω₀ with
+    /// k = sin(φ_max / 2). `None` where `libration` is.
+    pub fn exact_period(&self, y0: &DVector<f64>) -> Option<f64> {
+        self.libration(y0).map(|lib| 4.0 * lib.quarter / lib.omega0)
+    }
+
+    /// The frames `solve_from_state` would record (same times and `stride`), evaluated from
+    /// the closed-form single-pendulum solution instead of integrating. `None` where
+    /// `libration` is.
+    pub fn exact_trajectory(
+        &self,
+        y0: &DVector<f64>,
+        t_max: f64,
+        n_points: usize,
+        stride: usize,
+    ) -> Option<Trajectory> {
+        let lib = self.libration(y0)?;
+        let dt = t_max / (n_points - 1) as f64;
+        let steps = (0..n_points).filter(|step| step % stride == 0 || *step == n_points - 1);
+        let (t_axis, sol) = steps
+            .map(|step| {
+                let t = step as f64 * dt;
+                // The start exactly as given: the closed form only reproduces it to rounding,
+                // which would turn ω = 0 into a spurious sign
+                (t, if step == 0 { y0.clone() } else { lib.state_at(t) })
+            })
+            .unzip();
+        Some(Trajectory { t_axis, sol, first_collision_time: None, blew_up_at_time: None })
+    }
+
+    /// Kapitza effective potential (see `NPendulumMath::effective_potential`) at angles
+    /// θ1..θn for a pivot driven vertically with `amplitude` (m) and `frequency` (rad/s).
+    pub fn effective_potential(
+        &self,
+        angles: &[f64],
+        amplitude: f64,
+        frequency: f64,
+    ) -> Result<f64, SimError> {
+        let mut full_angles = vec![0.0; self.n + 1];
+        full_angles[1..].copy_from_slice(angles);
+        self.math_with(full_angles, vec![0.0; self.n + 1])
+            .effective_potential(amplitude, frequency)
+            .ok_or(SimError::SingularMatrix)
+    }
+
+    /// True if the fully inverted configuration (every θ = π) is a local minimum of the
+    /// effective potential, i.e. the drive stabilizes it. The Hessian is approximated by
+    /// central finite differences and tested for positive definiteness.
+    pub fn inverted_stabilized(&self, amplitude: f64, frequency: f64) -> Result<bool, SimError> {
+        const H: f64 = 1e-4;
+
+        let n = self.n;
+        let v = |offsets: &[(usize, f64)]| -> Result<f64, SimError> {
+            let mut angles = vec![std::f64::consts::PI; n];
+            for &(j, d) in offsets {
+                angles[j] += d;
+            }
+            self.effective_potential(&angles, amplitude, frequency)
+        };
+
+        let v0 = v(&[])?;
+        let mut hessian = DMatrix::zeros(n, n);
+        for i in 0..n {
+            hessian[(i, i)] = (v(&[(i, H)])? - 2.0 * v0 + v(&[(i, -H)])?) / (H * H);
+            for j in 0..i {
+                let d = v(&[(i, H), (j, H)])? - v(&[(i, H), (j, -H)])? - v(&[(i, -H), (j, H)])?
+                    + v(&[(i, -H), (j, -H)])?;
+                hessian[(i, j)] = d / (4.0 * H * H);
+                hessian[(j, i)] = hessian[(i, j)];
+            }
+        }
+        Ok(hessian.cholesky().is_some())
+    }
+}
+
+/// Exact libration of a single pendulum: with φ the swing about the rest angle and ω₀ the
+/// small-oscillation frequency, sin(φ/2) = k sn(ω₀ t + u₀ | k²) and
+/// φ̇ = 2 k ω₀ cn(ω₀ t + u₀ | k²).
+struct Libration {
+    center: f64,  // Rest angle, on the same turn as the initial angle
+    omega0: f64,
+    k: f64,       // sin(φ_max / 2)
+    quarter: f64, // K(k²): a quarter period in units of ω₀ t
+    u0: f64,
+}
+
+impl Libration {
+    /// [θ, ω] at time `t`.
+    fn state_at(&self, t: f64) -> DVector<f64> {
+        let u = (self.omega0 * t + self.u0).rem_euclid(4.0 * self.quarter);
+        let (sn, cn) = jacobi_sn_cn(u, self.k * self.k);
+        let theta = self.center + 2.0 * (self.k * sn).asin();
+        DVector::from_vec(vec![theta, 2.0 * self.k * self.omega0 * cn])
+    }
+}
+
+/// Complete elliptic integral of the first kind K(m) = π / (2 AGM(1, √(1 − m))), m < 1.
+fn elliptic_k(m: f64) -> f64 {
+    let (mut a, mut b) = (1.0_f64, (1.0 - m).sqrt());
+    while (a - b).abs() > 1e-15 * a {
+        (a, b) = (0.5 * (a + b), (a * b).sqrt());
+    }
+    std::f64::consts::PI / (2.0 * a)
+}
+
+/// Incomplete elliptic integral of the first kind F(φ | m) for |φ| ≤ π/2, via Carlson's
+/// R_F: F = sin φ R_F(cos²φ, 1 − m sin²φ, 1).
+fn elliptic_f(phi: f64, m: f64) -> f64 {
+    let (sin, cos) = phi.sin_cos();
+    let (mut x, mut y, mut z) = (cos * cos, 1.0 - m * sin * sin, 1.0);
+    loop {
+        let mu = (x + y + z) / 3.0;
+        let (dx, dy) = (1.0 - x / mu, 1.0 - y / mu);
+        let dz = -(dx + dy);
+        if dx.abs().max(dy.abs()).max(dz.abs()) < 1e-4 {
+            // Fifth-order series in the (now tiny) deviations from the mean
+            let e2 = dx * dy - dz * dz;
+            let e3 = dx * dy * dz;
+            let series = 1.0 - e2 / 10.0 + e3 / 14.0 + e2 * e2 / 24.0 - 3.0 * e2 * e3 / 44.0;
+            return sin * series / mu.sqrt();
+        }
+        let lambda = (x * y).sqrt() + (x * z).sqrt() + (y * z).sqrt();
+        (x, y, z) = (0.25 * (x + lambda), 0.25 * (y + lambda), 0.25 * (z + lambda));
+    }
+}
+
+/// Jacobi elliptic functions (sn, cn)(u | m), 0 ≤ m < 1, by the descending AGM
+/// (Abramowitz & Stegun 16.4).
+fn jacobi_sn_cn(u: f64, m: f64) -> (f64, f64) {
+    const MAX_ITER: usize = 16;
+
+    let mut a = vec![1.0_f64];
+    let mut c = vec![m.sqrt()];
+    let mut b = (1.0 - m).sqrt();
+    while c[c.len() - 1].abs() > 1e-15 && a.len() <= MAX_ITER {
+        let prev = a[a.len() - 1];
+        a.push(0.5 * (prev + b));
+        c.push(0.5 * (prev - b));
+        b = (prev * b).sqrt();
+    }
+    let last = a.len() - 1;
+    let mut phi = 2f64.powi(last as i32) * a[last] * u;
+    for i in (1..=last).rev() {
+        phi = 0.5 * (phi + (c[i] / a[i] * phi.sin()).asin());
+    }
+    phi.sin_cos()
+}
+
+/// A run paused at time `t` in state `y`, with the solver configuration that produced it
+/// (masses, lengths, gravity, topology, fixed links, damping). Continuing from it with the
+/// same step size retraces the uninterrupted run exactly: the dynamics are autonomous and
+/// serde_json round-trips every f64 bit for bit.
+#[derive(Deserialize, Serialize)]
+pub struct Checkpoint {
+    pub solver: NPendulumSolver,
+    pub t: f64,
+    pub y: Vec<f64>, // [θ1..θn, ω1..ωn]
+}
+
+/// Writes `checkpoint` to `path` as JSON.
+pub fn save_checkpoint(path: &Path, checkpoint: &Checkpoint) -> std::io::Result<()> {
+    std::fs::write(path, serde_json::to_vec_pretty(checkpoint)?)
+}
+
+/// Reads a checkpoint written by `save_checkpoint`. Fails with `InvalidData` if the file is
+/// not a checkpoint or its state does not match the solver's size.
+pub fn load_checkpoint(path: &Path) -> std::io::Result<Checkpoint> {
+    let checkpoint: Checkpoint = serde_json::from_slice(&std::fs::read(path)?)?;
+    let expected = 2 * checkpoint.solver.n;
+    if checkpoint.y.len() != expected {
+        let reason = format!("state has {} values, expected {expected}", checkpoint.y.len());
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, reason));
+    }
+    Ok(checkpoint)
+}
+
+/// Ensures a 1-based vector [0, v1, ..., vn] carries exactly `n` values after its padding
+/// entry, so slicing `[1..=n]` cannot panic or silently drop extra values.
+fn check_one_based<T>(field: &'static str, values: &[T], n: usize) -> Result<(), SimError> {
+    if values.len() != n + 1 {
+        return Err(SimError::LengthMismatch {
+            field,
+            expected: n,
+            got: values.len().saturating_sub(1),
+        });
+    }
+    Ok(())
+}
+
+/// ∫ f dt over samples `values` spaced `dt` apart. Simpson's rule needs an even number of
+/// intervals; with an odd number it covers all but the last, which gets the trapezoid.
+/// Fewer than two samples integrate to 0.
+pub fn integrate(values: &[f64], dt: f64, method: Quadrature) -> f64 {
+    let trapezoid = |v: &[f64]| v.windows(2).map(|w| 0.5 * (w[0] + w[1])).sum::<f64>() * dt;
+    match method {
+        _ if values.len() < 2 => 0.0,
+        Quadrature::Trapezoidal => trapezoid(values),
+        Quadrature::Simpson => {
+            let intervals = values.len() - 1;
+            let even = intervals - intervals % 2;
+            let simpson = values[..=even]
+                .windows(3)
+                .step_by(2)
+                .map(|w| w[0] + 4.0 * w[1] + w[2])
+                .sum::<f64>()
+                * dt
+                / 3.0;
+            simpson + trapezoid(&values[even..])
+        }
+    }
+}
+
+/// Poincaré section: every time θ_cross (1-based link index) crosses zero in `direction`,
+/// records the (θ_record, ω_record) pair, linearly interpolated to the crossing instant.
+pub fn poincare_section(
+    sol: &[DVector<f64>],
+    n: usize,
+    cross_link: usize,
+    record_link: usize,
+    direction: CrossingDirection,
+) -> Vec<[f64; 2]> {
+    let c = cross_link - 1;
+    let (th, om) = (record_link - 1, n + record_link - 1);
+    let mut points = Vec::new();
+
+    for pair in sol.windows(2) {
+        let (prev, curr) = (&pair[0], &pair[1]);
+        let upward = prev[c] < 0.0 && curr[c] >= 0.0;
+        let downward = prev[c] > 0.0 && curr[c] <= 0.0;
+        let crossed = match direction {
+            CrossingDirection::Positive => upward,
+            CrossingDirection::Negative => downward,
+            CrossingDirection::Both => upward || downward,
+        };
+
+        if crossed {
+            // Fraction of the step at which θ_cross hits zero
+            let s = prev[c] / (prev[c] - curr[c]);
+            points.push([
+                prev[th] + s * (curr[th] - prev[th]),
+                prev[om] + s * (curr[om] - prev[om]),
+            ]);
+        }
+    }
+    points
+}
+
+/// Integrates the elastic-link chain (see `SpringPendulumMath`) with RK4.
+/// State layout: [θ1..θn, r1..rn, ω1..ωn, ṙ1..ṙn].
+pub struct SpringPendulumSolver {
+    pub n: usize,
+    pub masses: Vec<f64>,       // 1-based, like `NPendulumSolver`
+    pub rest_lengths: Vec<f64>,
+    pub stiffness: Vec<f64>,
+    pub gx: f64,
+    pub gy: f64,
+}
+
+impl SpringPendulumSolver {
+    pub fn new(n: usize, masses: Vec<f64>, rest_lengths: Vec<f64>, stiffness: Vec<f64>) -> Self {
+        Self { n, masses, rest_lengths, stiffness, gx: 0.0, gy: -9.81 }
+    }
+
+    /// Sets gravity from a 2D vector; (0, −9.81) is the default.
+    pub fn with_gravity(mut self, gx: f64, gy: f64) -> Self {
+        self.gx = gx;
+        self.gy = gy;
+        self
+    }
+
+    /// Initial state at rest with every spring at its rest length.
+    pub fn initial_state(&self, angles: &[f64]) -> Result<DVector<f64>, SimError> {
+        let n = self.n;
+        check_one_based("initial_angles", angles, n)?;
+        let mut y = DVector::zeros(4 * n);
+        y.rows_mut(0, n).copy_from_slice(&angles[1..=n]);
+        y.rows_mut(n, n).copy_from_slice(&self.rest_lengths[1..=n]);
+        Ok(y)
+    }
+
+    /// Builds the 1-indexed math model for a state vector.
+    pub fn math_at(&self, y: &DVector<f64>) -> SpringPendulumMath {
+        let n = self.n;
+        let one_based = |offset: usize| {
+            let mut v = vec![0.0; n + 1];
+            v[1..=n].copy_from_slice(y.rows(offset, n).as_slice());
+            v
+        };
+        SpringPendulumMath {
+            gx: self.gx,
+            gy: self.gy,
+            n,
+            masses: self.masses.clone(),
+            rest_lengths: self.rest_lengths.clone(),
+            stiffness: self.stiffness.clone(),
+            angles: one_based(0),
+            radii: one_based(n),
+            ang_vels: one_based(2 * n),
+            radial_vels: one_based(3 * n),
+        }
+    }
 
-        (t_axis, sol)
+    /// Computes dy/dt = [q̇, q̈] with q = [θ, r] at time `t` (autonomous for now).
+    pub fn deriv(&self, _t: f64, y: &DVector<f64>) -> Result<DVector<f64>, SimError> {
+        let half = 2 * self.n;
+        let (mass, rhs) = self.math_at(y).equations();
+        let q_ddot = mass.lu().solve(&rhs).ok_or(SimError::SingularMatrix)?;
+
+        let mut dydt = DVector::zeros(2 * half);
+        dydt.rows_mut(0, half).copy_from(&y.rows(half, half));
+        dydt.rows_mut(half, half).copy_from(&q_ddot);
+        Ok(dydt)
+    }
+
+    /// Integrates over `n_points` steps, recording every `opts.stride`-th state.
+    /// Collision checks and `opts.warmup` are not supported for elastic links. `on_progress`
+    /// receives the fraction of steps completed after every step.
+    pub fn solve_from_state_with_progress(
+        &self,
+        mut y: DVector<f64>,
+        t_max: f64,
+        n_points: usize,
+        opts: &SolveOptions<f64>,
+        mut on_progress: impl FnMut(f64),
+    ) -> Result<Trajectory, SimError> {
+        let stride = opts.stride;
+        let dt = t_max / (n_points - 1) as f64;
+        let h = dt / opts.substeps as f64;
+        let n_frames = (n_points - 1).div_ceil(stride) + 1;
+
+        let mut t_axis = Vec::with_capacity(n_frames);
+        let mut sol = Vec::with_capacity(n_frames);
+
+        let mut blew_up_at_time = None;
+        let mut curr_t = 0.0;
+        for step in 0..n_points {
+            check_deadline(opts.deadline, step)?;
+            // State is [θ, r, ω, ṙ]
+            if blew_up(&y, 2 * self.n..3 * self.n, opts.max_ang_vel) {
+                blew_up_at_time = Some(curr_t);
+                break;
+            }
+            if step % stride == 0 || step == n_points - 1 {
+                t_axis.push(curr_t);
+                sol.push(y.clone());
+            }
+
+            for _ in 0..opts.substeps {
+                y = rk4(|t, y| self.deriv(t, y), curr_t, &y, h)?;
+                curr_t += h;
+            }
+            on_progress((step + 1) as f64 / n_points as f64);
+        }
+
+        Ok(Trajectory { t_axis, sol, first_collision_time: None, blew_up_at_time })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Double pendulum with m = (1, 2), l = (1, 0.5), released from rest at θ = (1.2, −0.5).
+    fn double_pendulum() -> (NPendulumSolver, DVector<f64>) {
+        let solver = NPendulumSolver::new(2, vec![0.0, 1.0, 2.0], vec![0.0, 1.0, 0.5]);
+        let y0 = solver.initial_state(&[0.0, 1.2, -0.5], &[0.0; 3]).unwrap();
+        (solver, y0)
+    }
+
+    /// State [θ1, θ2, ω1, ω2] at t = 1 s from the textbook closed-form double-pendulum
+    /// equations (not the M α + C + G = 0 form used here), integrated with mpmath's
+    /// 30-digit Taylor-series ODE solver.
+    const REFERENCE_AT_1S: [f64; 4] =
+        [-0.3597332595510846, -1.7481306489176627, -0.12239938021779659, -5.062614339608208];
+
+    #[test]
+    fn double_pendulum_matches_reference_trajectory() {
+        let (solver, y0) = double_pendulum();
+        let traj = solver.solve_from_state(y0, 1.0, 1001, &SolveOptions::default()).unwrap();
+        let y = traj.sol.last().unwrap();
+        for (got, want) in y.iter().zip(REFERENCE_AT_1S) {
+            assert!((got - want).abs() < 1e-8, "got {got}, want {want}");
+        }
+    }
+
+    #[test]
+    fn energy_is_conserved_for_small_steps() {
+        let solver: NPendulumSolver =
+            NPendulumSolver::new(3, vec![0.0, 1.0, 1.0, 1.0], vec![0.0, 1.0, 1.0, 1.0]);
+        let y0 = solver.initial_state(&[0.0, 2.0, 1.0, -0.5], &[0.0; 4]).unwrap();
+        let e0 = solver.total_energy(&y0);
+        let traj = solver.solve_from_state(y0, 2.0, 4001, &SolveOptions::default()).unwrap();
+        let drift = traj.sol.iter().map(|y| (solver.total_energy(y) - e0).abs()).fold(0.0, f64::max);
+        assert!(drift < 1e-8 * e0.abs(), "energy drifted by {drift} J");
+    }
+
+    #[test]
+    fn energy_holds_without_gravity_or_damping() {
+        // Only kinetic energy is left to exchange between the links
+        let solver: NPendulumSolver =
+            NPendulumSolver::new(3, vec![0.0, 1.0, 1.0, 1.0], vec![0.0, 1.0, 1.0, 1.0])
+                .with_gravity_enabled(false);
+        let y0 = solver.initial_state(&[0.0, 2.0, 1.0, -0.5], &[0.0, 1.0, -2.0, 0.5]).unwrap();
+        let e0 = solver.total_energy(&y0);
+        let traj = solver.solve_from_state(y0, 2.0, 4001, &SolveOptions::default()).unwrap();
+        let drift = traj.sol.iter().map(|y| (solver.total_energy(y) - e0).abs()).fold(0.0, f64::max);
+        assert!(e0 > 1.0);
+        assert!(drift < 1e-10 * e0, "energy drifted by {drift} J");
+    }
+
+    #[test]
+    fn angular_momentum_holds_without_gravity() {
+        // No external torque about the pivot is left once gravity is off
+        let solver: NPendulumSolver =
+            NPendulumSolver::new(3, vec![0.0, 1.0, 2.0, 0.5], vec![0.0, 1.0, 0.7, 1.2])
+                .with_gravity_enabled(false);
+        let y0 = solver.initial_state(&[0.0, 0.4, -1.3, 2.0], &[0.0, 1.0, -2.0, 0.5]).unwrap();
+        let traj = solver.solve_from_state(y0, 2.0, 4001, &SolveOptions::default()).unwrap();
+        let momenta: Vec<f64> = traj.sol.iter().map(|y| solver.angular_momentum(y)).collect();
+        let max = momenta.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let min = momenta.iter().copied().fold(f64::INFINITY, f64::min);
+        assert!(momenta[0].abs() > 0.1);
+        assert!(max - min < 1e-9 * momenta[0].abs(), "angular momentum spread {}", max - min);
+    }
+
+    #[test]
+    fn lyapunov_exponent_separates_regular_from_chaotic_motion() {
+        let opts = SolveOptions { stride: 100, substeps: 2, ..SolveOptions::default() };
+        let single = NPendulumSolver::new(1, vec![0.0, 1.0], vec![0.0, 1.0]);
+        let small = single.initial_state(&[0.0, 0.1], &[0.0; 2]).unwrap();
+        let (running, regular) = single.lyapunov_exponent(&small, 20.0, 2001, 1e-8, &opts).unwrap();
+        assert_eq!(running.len(), 21);
+        assert!(regular.abs() < 0.2, "small swing: λ = {regular}");
+
+        let (solver, _) = double_pendulum();
+        let wild = solver.initial_state(&[0.0, 2.5, 2.0], &[0.0; 3]).unwrap();
+        let (_, chaotic) = solver.lyapunov_exponent(&wild, 20.0, 2001, 1e-8, &opts).unwrap();
+        assert!(chaotic > 0.5, "chaotic swing: λ = {chaotic}");
+
+        let expired = Deadline { at: Instant::now(), limit_secs: 0.0 };
+        let opts = SolveOptions { deadline: Some(expired), ..opts };
+        let result = solver.lyapunov_exponent(&wild, 20.0, 2001, 1e-8, &opts);
+        assert!(matches!(result, Err(SimError::TimedOut { .. })));
+    }
+
+    #[test]
+    fn energy_is_conserved_on_a_branching_tree() {
+        // Y shape: link 1 hangs from the pivot, links 2 and 3 both hang from its bob
+        let solver: NPendulumSolver =
+            NPendulumSolver::new(3, vec![0.0, 1.0, 0.5, 0.8], vec![0.0, 1.0, 0.7, 0.9])
+                .with_topology(Some(vec![0, 0, 1, 1]));
+        let y0 = solver.initial_state(&[0.0, 1.5, 0.4, -1.0], &[0.0; 4]).unwrap();
+        let e0 = solver.total_energy(&y0);
+        let traj = solver.solve_from_state(y0, 3.0, 6001, &SolveOptions::default()).unwrap();
+        assert_eq!(traj.sol.len(), 6001);
+        let drift = traj.sol.iter().map(|y| (solver.total_energy(y) - e0).abs()).fold(0.0, f64::max);
+        assert!(drift < 1e-8 * e0.abs(), "energy drifted by {drift} J");
+        // The topology reaches the integrator: the same links as a chain move differently
+        let chain = NPendulumSolver::new(3, solver.masses.clone(), solver.lengths.clone());
+        let opts = SolveOptions::default();
+        let chain_end = chain.solve_from_state(traj.sol[0].clone(), 3.0, 6001, &opts).unwrap();
+        let chain_end = chain_end.sol.last().unwrap();
+        assert!((chain_end - traj.sol.last().unwrap()).norm() > 1e-2);
+    }
+
+    #[test]
+    fn jacobian_at_rest_has_the_normal_mode_frequencies() {
+        let (solver, _) = double_pendulum();
+        let jac = solver.jacobian(0.0, &DVector::zeros(4)).unwrap();
+        let eigenvalues = jac.complex_eigenvalues();
+        assert!(eigenvalues.iter().all(|z| z.re.abs() < 1e-6), "{eigenvalues}");
+        // ±iω per mode: every frequency shows up twice
+        let mut frequencies: Vec<f64> = eigenvalues.iter().map(|z| z.im.abs()).collect();
+        frequencies.sort_by(f64::total_cmp);
+        let modes = solver.normal_mode_frequencies().unwrap();
+        assert_eq!(modes.len(), 2);
+        for (pair, omega) in frequencies.chunks(2).zip(&modes) {
+            for f in pair {
+                assert!((f - omega).abs() < 1e-6, "{f} vs {omega}");
+            }
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn oversized_steps_stop_at_the_blow_up() {
+        // dt = 1 s is far too coarse for this motion: RK4 diverges within a few steps
+        let (solver, y0) = double_pendulum();
+        let traj = solver.solve_from_state(y0, 60.0, 61, &SolveOptions::default()).unwrap();
+        let time = traj.blew_up_at_time.expect("run should be flagged as unstable");
+        assert!(time < 60.0);
+        assert!(traj.sol.iter().all(|y| y.iter().all(|v| v.is_finite())));
+        assert!(traj.t_axis.iter().all(|&t| t < time));
+    }
+
+    #[test]
+    fn angular_velocity_threshold_stops_the_run() {
+        let (solver, y0) = double_pendulum();
+        let opts = SolveOptions { max_ang_vel: Some(2.0), ..SolveOptions::default() };
+        let traj = solver.solve_from_state(y0, 5.0, 501, &opts).unwrap();
+        assert!(traj.blew_up_at_time.is_some());
+        let last = traj.sol.last().unwrap();
+        assert!(last.rows(2, 2).iter().all(|w| w.abs() <= 2.0));
+    }
+
+    #[test]
+    fn free_link_on_a_fixed_base_swings_like_a_simple_pendulum() {
+        // Link 1 pinned horizontal: link 2 sees a pivot moved to (1, 0) and nothing else
+        let base = NPendulumSolver::new(2, vec![0.0, 1.0, 2.0], vec![0.0, 1.0, 0.5])
+            .with_fixed_links(Some(vec![false, true, false]));
+        let half_pi = std::f64::consts::FRAC_PI_2;
+        let y0 = base.initial_state(&[0.0, half_pi, 0.3], &[0.0, 0.8, 0.0]).unwrap();
+        let pinned = base.solve_from_state(y0, 2.0, 201, &SolveOptions::default()).unwrap();
+
+        let single = NPendulumSolver::new(1, vec![0.0, 2.0], vec![0.0, 0.5]);
+        let y0 = single.initial_state(&[0.0, 0.3], &[0.0; 2]).unwrap();
+        let simple = single.solve_from_state(y0, 2.0, 201, &SolveOptions::default()).unwrap();
+
+        for (y, reference) in pinned.sol.iter().zip(&simple.sol) {
+            assert_eq!((y[0], y[2]), (half_pi, 0.0));
+            assert!((y[1] - reference[0]).abs() < 1e-12);
+            assert!((y[3] - reference[1]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn kapitza_drive_stabilizes_the_inverted_pendulum_above_threshold() {
+        // One link: V_eff = −m g l cos θ + m a² Ω² sin² θ / 4, inverted stable iff a²Ω² > 2 g l
+        let solver = NPendulumSolver::new(1, vec![0.0, 2.0], vec![0.0, 0.5]);
+        let (a, theta) = (0.05, 2.5_f64);
+        let threshold = (2.0 * 9.81 * 0.5_f64).sqrt() / a;
+
+        let v = solver.effective_potential(&[theta], a, 200.0).unwrap();
+        let expected =
+            -2.0 * 9.81 * 0.5 * theta.cos() + 2.0 * (a * 200.0).powi(2) * theta.sin().powi(2) / 4.0;
+        assert!((v - expected).abs() < 1e-9);
+
+        assert!(!solver.inverted_stabilized(a, 0.9 * threshold).unwrap());
+        assert!(solver.inverted_stabilized(a, 1.1 * threshold).unwrap());
+    }
+
+    #[test]
+    fn energy_correction_holds_the_initial_energy() {
+        let (solver, y0) = double_pendulum();
+        let e0 = solver.total_energy(&y0);
+        let opts = SolveOptions { energy_correction: true, ..SolveOptions::default() };
+        let traj = solver.solve_from_state(y0, 5.0, 501, &opts).unwrap();
+        let drift = traj.sol.iter().map(|y| (solver.total_energy(y) - e0).abs()).fold(0.0, f64::max);
+        assert!(drift < 1e-10, "energy drifted by {drift} J");
+    }
+
+    #[test]
+    fn measured_period_matches_the_elliptic_integral() {
+        let solver: NPendulumSolver = NPendulumSolver::new(1, vec![0.0, 1.0], vec![0.0, 1.0]);
+        let small = 2.0 * std::f64::consts::PI / solver.g.sqrt();
+        for amplitude in [0.1_f64, 1.0, 2.0, 3.0] {
+            let y0 = solver.initial_state(&[0.0, amplitude], &[0.0; 2]).unwrap();
+            let exact = solver.exact_period(&y0).unwrap();
+            assert!(exact > small, "{amplitude} rad: {exact} s");
+            let traj = solver.solve_from_state(y0, 1.2 * exact, 2001, &SolveOptions::default());
+            let measured = solver.measure_period(&traj.unwrap()).unwrap().unwrap();
+            let error = (measured - exact).abs() / exact;
+            assert!(error < 1e-8, "{amplitude} rad: {measured} vs {exact}");
+        }
+        // Over the top there is no libration
+        let spinning = DVector::from_vec(vec![0.0, 7.0]);
+        assert_eq!(solver.exact_period(&spinning), None);
+    }
+
+    #[test]
+    fn uniform_rod_swings_with_the_physical_pendulum_period() {
+        // I = m L² / 3 about the pivot, centre of mass at L / 2: ω² = 3 g / (2 L)
+        let length = 0.8;
+        let solver: NPendulumSolver =
+            NPendulumSolver::new(1, vec![0.0, 1.3], vec![0.0, length]).with_rod_inertia(true);
+        let omega = (3.0 * solver.g / (2.0 * length)).sqrt();
+        let frequencies = solver.normal_mode_frequencies().unwrap();
+        assert!((frequencies[0] - omega).abs() < 1e-12, "{} vs {omega}", frequencies[0]);
+
+        let period = 2.0 * std::f64::consts::PI * (2.0 * length / (3.0 * solver.g)).sqrt();
+        let y0 = solver.initial_state(&[0.0, 1e-3], &[0.0; 2]).unwrap();
+        let traj = solver.solve_from_state(y0, 1.2 * period, 2001, &SolveOptions::default());
+        let measured = solver.measure_period(&traj.unwrap()).unwrap().unwrap();
+        assert!((measured - period).abs() < 1e-6 * period, "{measured} vs {period}");
+    }
+
+    #[test]
+    fn exact_trajectory_tracks_rk4_from_a_moving_start() {
+        let solver = NPendulumSolver::new(1, vec![0.0, 1.0], vec![0.0, 0.7]).with_rod_inertia(true);
+        let y0 = DVector::from_vec(vec![2.0, -1.5]);
+        let exact = solver.exact_trajectory(&y0, 4.0, 801, 10).unwrap();
+        let opts = SolveOptions { stride: 10, ..Default::default() };
+        let traj = solver.solve_from_state(y0, 4.0, 801, &opts).unwrap();
+        assert_eq!(exact.t_axis.len(), traj.t_axis.len());
+        for (a, b) in exact.sol.iter().zip(&traj.sol) {
+            assert!((a - b).norm() < 1e-7, "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn warmup_skips_the_transient_and_shifts_the_time_axis() {
+        let (solver, y0) = double_pendulum();
+        // dt = 0.1 either way, so the warmed-up run retraces the tail of the long one
+        let long = solver.solve_from_state(y0.clone(), 1.5, 16, &SolveOptions::default()).unwrap();
+        let opts = SolveOptions { warmup: Some(0.5), ..SolveOptions::default() };
+        let warm = solver.solve_from_state(y0, 1.0, 11, &opts).unwrap();
+        assert_eq!(warm.sol.len(), 11);
+        assert!((warm.t_axis[0] - 0.5).abs() < 1e-12, "{}", warm.t_axis[0]);
+        for (k, y) in warm.sol.iter().enumerate() {
+            assert!((warm.t_axis[k] - long.t_axis[k + 5]).abs() < 1e-12);
+            assert!((y - &long.sol[k + 5]).norm() < 1e-12, "frame {k}");
+        }
+    }
+
+    #[test]
+    fn hamiltonian_is_conserved_while_the_lagrangian_oscillates() {
+        let (solver, y0) = double_pendulum();
+        let traj = solver.solve_from_state(y0, 5.0, 1001, &SolveOptions::default()).unwrap();
+        let (lagrangian, hamiltonian): (Vec<f64>, Vec<f64>) =
+            traj.sol.iter().map(|y| solver.lagrangian_and_hamiltonian(y)).unzip();
+        let spread = |v: &[f64]| {
+            v.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b))
+                - v.iter().fold(f64::INFINITY, |a, &b| a.min(b))
+        };
+        let (h_spread, l_spread) = (spread(&hamiltonian), spread(&lagrangian));
+        assert!(l_spread > 1.0, "L only varied by {l_spread} J");
+        assert!(h_spread < 1e-4 * l_spread, "H drifted by {h_spread} J");
+        assert!((hamiltonian[0] - solver.total_energy(&traj.sol[0])).abs() < 1e-12);
+    }
+
+    #[test]
+    fn mass_matrix_condition_peaks_when_the_links_line_up() {
+        // m = l = 1: M = [[2, c], [c, 1]] with c = cos(θ1 − θ2)
+        let solver = NPendulumSolver::new(2, vec![0.0, 1.0, 1.0], vec![0.0, 1.0, 1.0]);
+        let condition = |theta2: f64| {
+            solver.mass_matrix_condition(&DVector::from_vec(vec![0.0, theta2, 0.0, 0.0]))
+        };
+        let root5 = 5.0_f64.sqrt();
+        assert!((condition(0.0) - (3.0 + root5) / (3.0 - root5)).abs() < 1e-12);
+        assert!((condition(std::f64::consts::FRAC_PI_2) - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn frame_callback_can_stop_the_run_early() {
+        const K: usize = 4;
+        let (solver, y0) = double_pendulum();
+        let opts = SolveOptions { stride: 10, ..SolveOptions::default() };
+        let full = solver.solve_from_state(y0.clone(), 1.0, 101, &opts).unwrap();
+        let mut seen = Vec::new();
+        let stopped = solver
+            .solve_from_state_until(y0, 1.0, 101, &opts, |step, t, y| {
+                seen.push((step, t, y.clone()));
+                if seen.len() == K { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+            })
+            .unwrap();
+        assert_eq!(stopped.sol.len(), K);
+        assert_eq!(seen.iter().map(|f| f.0).collect::<Vec<_>>(), [0, 10, 20, 30]);
+        for (k, (_, t, y)) in seen.iter().enumerate() {
+            assert_eq!((*t, y), (full.t_axis[k], &full.sol[k]));
+            assert_eq!((stopped.t_axis[k], &stopped.sol[k]), (full.t_axis[k], &full.sol[k]));
+        }
+    }
+
+    #[test]
+    fn resuming_from_a_checkpoint_matches_the_uninterrupted_run() {
+        let build = || {
+            let coefficients = vec![0.0, 0.1, 0.05];
+            let damping = Damping { model: DampingModel::Linear, coefficients };
+            double_pendulum()
+                .0
+                .with_gravity(1.0, -9.0)
+                .with_rod_inertia(true)
+                .with_damping(Some(damping))
+        };
+        let (_, y0) = double_pendulum();
+        let opts = SolveOptions::default();
+        let full = build().solve_from_state(y0.clone(), 2.0, 2001, &opts).unwrap();
+
+        let solver = build();
+        let half = solver.solve_from_state(y0, 1.0, 1001, &opts).unwrap();
+        let y = half.sol.last().unwrap().as_slice().to_vec();
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.json", std::process::id()));
+        save_checkpoint(&path, &Checkpoint { solver, t: 1.0, y }).unwrap();
+
+        let Checkpoint { solver, t, y } = load_checkpoint(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(t, 1.0);
+        let rest = solver.solve_from_state(DVector::from_vec(y), 1.0, 1001, &opts).unwrap();
+        assert_eq!(rest.sol.last(), full.sol.last());
+    }
+
+    #[test]
+    fn every_damping_model_drains_energy_monotonically() {
+        for model in [DampingModel::Linear, DampingModel::Quadratic, DampingModel::Coulomb] {
+            let (solver, y0) = double_pendulum();
+            let coefficients = vec![0.0, 0.2, 0.1];
+            let solver = solver.with_damping(Some(Damping { model, coefficients }));
+            let e0 = solver.total_energy(&y0);
+            let traj = solver.solve_from_state(y0, 5.0, 501, &SolveOptions::default()).unwrap();
+            let energies: Vec<f64> = traj.sol.iter().map(|y| solver.total_energy(y)).collect();
+            for pair in energies.windows(2) {
+                assert!(pair[1] <= pair[0] + 1e-9, "{model:?}: energy rose {pair:?}");
+            }
+            let lost = e0 - energies.last().unwrap();
+            assert!(lost > 0.05, "{model:?}: only {lost} J dissipated");
+        }
+    }
+
+    #[test]
+    fn flip_time_interpolates_the_crossing_and_respects_the_energy_barrier() {
+        let (solver, low) = double_pendulum();
+        assert_eq!(solver.flip_time(low, 2, 10.0, 0.01, None).unwrap(), None);
+
+        // Link 2 at 0.1 rad below the top, swinging up at 3 rad/s: over in about 1/30 s
+        let pi = std::f64::consts::PI;
+        let rising = DVector::from_vec(vec![0.0, pi - 0.1, 0.0, 3.0]);
+        let t = solver.flip_time(rising, 2, 1.0, 0.01, None).unwrap().expect("flips");
+        assert!((t - 0.1 / 3.0).abs() < 2e-3, "flipped at {t}");
+
+        // A turn later (θ2 just below 3π) the same swing flips at the same time, and falling
+        // back through −π counts too
+        let wound = DVector::from_vec(vec![0.0, 3.0 * pi - 0.1, 0.0, 3.0]);
+        let t_wound = solver.flip_time(wound, 2, 1.0, 0.01, None).unwrap().expect("flips");
+        assert!((t_wound - t).abs() < 1e-9, "{t_wound} vs {t}");
+        let falling = DVector::from_vec(vec![0.0, 0.1 - pi, 0.0, -3.0]);
+        let t_fall = solver.flip_time(falling, 2, 1.0, 0.01, None).unwrap().expect("flips");
+        assert!((t_fall - t).abs() < 1e-9, "{t_fall} vs {t}");
+    }
+
+    #[test]
+    fn simpson_beats_the_trapezoid_on_smooth_integrands() {
+        // ∫₀^π sin t dt = 2
+        let dt = std::f64::consts::PI / 20.0;
+        let samples: Vec<f64> = (0..=20).map(|i| (i as f64 * dt).sin()).collect();
+        let trapezoid = integrate(&samples, dt, Quadrature::Trapezoidal);
+        let simpson = integrate(&samples, dt, Quadrature::Simpson);
+        assert!((trapezoid - 2.0).abs() < 5e-3);
+        assert!((simpson - 2.0).abs() < 1e-5);
+
+        // Odd interval count: Simpson plus one trapezoid, still exact for a straight line
+        assert!((integrate(&[0.0, 1.0, 2.0, 3.0], 1.0, Quadrature::Simpson) - 4.5).abs() < 1e-12);
+        assert_eq!(integrate(&[1.0], 1.0, Quadrature::Simpson), 0.0);
+    }
+
+    #[test]
+    fn seeded_perturbations_repeat_and_have_the_requested_spread() {
+        let perturbed = |seed| {
+            let mut values = vec![0.0; 20_000];
+            perturb(&mut values, 0.5, seed);
+            values
+        };
+        let values = perturbed(1);
+        assert_eq!(values, perturbed(1));
+        assert_ne!(values, perturbed(2));
+
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+        assert!(mean.abs() < 0.02, "mean {mean}");
+        assert!((var.sqrt() - 0.5).abs() < 0.02, "std dev {}", var.sqrt());
+    }
+
+    #[test]
+    fn backward_run_retraces_the_forward_trajectory() {
+        let (solver, y0) = double_pendulum();
+        let opts = SolveOptions { substeps: 4, ..SolveOptions::default() };
+        let forward = solver.solve_from_state(y0, 1.0, 201, &opts).unwrap();
+        let end = forward.sol[forward.sol.len() - 1].clone();
+        let backward = solver.solve_from_state(end, -1.0, 201, &opts).unwrap();
+
+        assert!(backward.t_axis.windows(2).all(|w| w[1] < w[0]));
+        assert!((backward.t_axis[200] + 1.0).abs() < 1e-12);
+        for (back, fwd) in backward.sol.iter().zip(forward.sol.iter().rev()) {
+            assert!((back - fwd).norm() < 1e-7, "off by {}", (back - fwd).norm());
+        }
+
+        // Interpolation works on the decreasing axis too
+        let mid = backward.state_at(-0.5).unwrap();
+        assert!((mid - forward.state_at(0.5).unwrap()).norm() < 1e-7);
+    }
+
+    #[test]
+    fn energy_shell_sampling_is_seeded_and_hits_the_target() {
+        let (solver, _) = double_pendulum();
+        let sample = |seed| solver.sample_at_energy(5.0, seed).unwrap();
+        let y = sample(7);
+        assert!((solver.total_energy(&y) - 5.0).abs() < 1e-12);
+        assert_eq!(y, sample(7));
+        assert_ne!(y, sample(8));
+    }
+}
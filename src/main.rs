@@ -1,38 +1,230 @@
 // src/main.rs
+use actix_cors::Cors;
 use actix_files::Files;
-use actix_web::{middleware, web, App, HttpServer};
+use actix_web::{http::header, middleware, web, App, HttpServer};
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+use n_pendulum_sim::{cache, metrics, ratelimit, ui};
+use tracing_subscriber::EnvFilter;
 
-mod logic;
-mod math;
-mod ui;
+/// Reads the comma-separated `CORS_ALLOWED_ORIGINS` env var.
+/// An empty list means no cross-origin access (same-origin only).
+fn allowed_origins() -> Vec<String> {
+    env::var("CORS_ALLOWED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|o| o.trim().to_string())
+        .filter(|o| !o.is_empty())
+        .collect()
+}
+
+/// Reads `SIMULATE_TIMEOUT_SECS`, the per-request wall-clock limit for simulations.
+fn solve_timeout() -> ui::SolveTimeout {
+    let secs: f64 = env::var("SIMULATE_TIMEOUT_SECS")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .expect("SIMULATE_TIMEOUT_SECS must be a number");
+    assert!(secs > 0.0, "SIMULATE_TIMEOUT_SECS must be positive");
+    ui::SolveTimeout(Duration::from_secs_f64(secs))
+}
+
+/// Reads `SHUTDOWN_TIMEOUT_SECS`, how long in-flight requests may keep running after SIGTERM.
+/// Defaults to the simulation limit plus a few seconds, so a solve that has just started can
+/// still finish (or hit its own `timed_out`) and be sent before the workers are stopped.
+fn shutdown_timeout(solve: ui::SolveTimeout) -> u64 {
+    let default = solve.0.as_secs_f64().ceil() as u64 + 5;
+    let secs: u64 = env::var("SHUTDOWN_TIMEOUT_SECS")
+        .map(|v| v.parse().expect("SHUTDOWN_TIMEOUT_SECS must be a whole number of seconds"))
+        .unwrap_or(default);
+    if Duration::from_secs(secs) <= solve.0 {
+        tracing::warn!(
+            "SHUTDOWN_TIMEOUT_SECS ({secs}) does not exceed SIMULATE_TIMEOUT_SECS; \
+             in-flight solves may be cut off on shutdown"
+        );
+    }
+    secs
+}
+
+/// Reads `MAX_BODY_BYTES`, the largest JSON request body accepted. Bodies are buffered
+/// before deserialization, so this bounds memory per request ahead of any validation.
+fn max_body_bytes() -> usize {
+    let bytes: usize = env::var("MAX_BODY_BYTES")
+        .unwrap_or_else(|_| (1024 * 1024).to_string())
+        .parse()
+        .expect("MAX_BODY_BYTES must be a whole number of bytes");
+    assert!(bytes > 0, "MAX_BODY_BYTES must be positive");
+    bytes
+}
+
+/// Reads `RESPONSE_CACHE_SIZE`, how many encoded `/simulate` responses to keep for repeated
+/// identical requests (0 disables the cache).
+fn response_cache_size() -> usize {
+    env::var("RESPONSE_CACHE_SIZE")
+        .unwrap_or_else(|_| "64".to_string())
+        .parse()
+        .expect("RESPONSE_CACHE_SIZE must be a whole number of entries")
+}
+
+/// Reads `RATE_LIMIT_RPS` and `RATE_LIMIT_BURST`, the per-client-IP request rate allowed on
+/// the simulation endpoints and how many requests may arrive at once. Unset (or 0) rate
+/// disables the limit; the burst defaults to one second's worth of requests.
+fn rate_limiter() -> ratelimit::RateLimiter {
+    let rate: f64 = env::var("RATE_LIMIT_RPS")
+        .map(|v| v.parse().expect("RATE_LIMIT_RPS must be a number"))
+        .unwrap_or(0.0);
+    assert!(rate.is_finite() && rate >= 0.0, "RATE_LIMIT_RPS must be non-negative");
+    let burst: f64 = env::var("RATE_LIMIT_BURST")
+        .map(|v| v.parse().expect("RATE_LIMIT_BURST must be a number"))
+        .unwrap_or(rate.ceil().max(1.0));
+    assert!(burst.is_finite() && burst >= 1.0, "RATE_LIMIT_BURST must be at least 1");
+    if rate > 0.0 {
+        tracing::info!("Rate limit: {rate} requests/s per client IP, bursts of {burst}");
+    }
+    ratelimit::RateLimiter::new(rate, burst)
+}
+
+/// Reads `WORKERS`, the number of actix worker threads. Defaults to one per logical core,
+/// like actix itself; set it lower on shared hosts. Workers only parse requests and encode
+/// responses: solves run on each worker's blocking pool, and large chains and `/flip_map` also
+/// use rayon's global pool (one thread per core), so this does not bound the CPU of solving.
+fn workers() -> usize {
+    let workers: usize = match env::var("WORKERS") {
+        Ok(v) => v.parse().expect("WORKERS must be a whole number of threads"),
+        Err(_) => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    assert!(workers >= 1, "WORKERS must be at least 1");
+    workers
+}
+
+/// Reads `STATIC_DIR`, the frontend asset directory served at `/`.
+/// Checked at startup so a bad path fails fast instead of 404ing every page.
+fn static_dir() -> PathBuf {
+    let dir = PathBuf::from(env::var("STATIC_DIR").unwrap_or_else(|_| "./static".to_string()));
+    assert!(dir.is_dir(), "STATIC_DIR '{}' is not an existing directory", dir.display());
+    dir
+}
+
+/// Builds the CORS policy for the API: GET, and POST (plus its preflight) with a JSON body.
+fn cors_policy(origins: &[String]) -> Cors {
+    origins
+        .iter()
+        .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        .allowed_methods(vec!["GET", "POST"])
+        .allowed_header(header::CONTENT_TYPE)
+        .max_age(3600)
+}
+
+/// Installs the JSON `tracing` subscriber (which also picks up the `log` records of actix's
+/// Logger middleware) and a panic hook that logs panics before the default report.
+fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("actix_web=info,n_pendulum_sim=info"));
+    tracing_subscriber::fmt()
+        .json()
+        .with_current_span(true)
+        .with_span_list(false)
+        .with_env_filter(filter)
+        .init();
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!(panic = %info, "panic");
+        default_hook(info);
+    }));
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // 1. Initialize the logger so Actix can output to the console
-    // "info" means show all info, warnings, and errors.
-    std::env::set_var("RUST_LOG", "actix_web=info");
-    env_logger::init();
+    // 1. Initialize structured (JSON lines) logging. `RUST_LOG` picks the levels; by default
+    // the access log and our per-request spans are shown at info.
+    init_tracing();
 
+    let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
     let port: u16 = env::var("PORT")
         .unwrap_or_else(|_| "8080".to_string())
         .parse()
         .expect("PORT must be a number");
 
-    println!("Starting server on 0.0.0.0:{}", port);
+    let origins = allowed_origins();
+    if !origins.is_empty() {
+        tracing::info!("CORS enabled for: {}", origins.join(", "));
+    }
+
+    let timeout = solve_timeout();
+    let shutdown_secs = shutdown_timeout(timeout);
+    let static_dir = static_dir();
+    let body_limit = max_body_bytes();
+    let scenario_path = env::var("SCENARIO_PATH").ok().filter(|path| !path.is_empty());
+    let scenario = web::Data::new(ui::DefaultScenario::load(scenario_path.as_deref()));
+    // One registry for all workers, so every scrape sees the totals
+    let metrics = web::Data::new(metrics::Metrics::new());
+    let cache = web::Data::new(cache::ResponseCache::new(response_cache_size()));
+    // Shared, so a client's budget does not depend on which worker takes the connection
+    let limiter = web::Data::new(rate_limiter());
+    let workers = workers();
+
+    tracing::info!(
+        "Starting server on {host}:{port} with {workers} workers (static files from {})",
+        static_dir.display()
+    );
 
-    HttpServer::new(|| {
+    HttpServer::new(move || {
         App::new()
+            .app_data(web::Data::new(timeout))
+            .app_data(scenario.clone())
+            .app_data(metrics.clone())
+            .app_data(cache.clone())
+            .app_data(limiter.clone())
+            .app_data(
+                web::JsonConfig::default()
+                    .limit(body_limit)
+                    .error_handler(ui::json_error_handler),
+            )
             // 2. Wrap the app in the Logger middleware
             .wrap(middleware::Logger::default())
-            .route("/simulate", web::post().to(ui::simulate_handler))
+            // 3. Compress responses according to the client's Accept-Encoding
+            .wrap(middleware::Compress::default())
+            // 4. Allow the configured origins to call the API (disabled when unset)
+            .wrap(middleware::Condition::new(
+                !origins.is_empty(),
+                cors_policy(&origins),
+            ))
+            // 5. Throttle the simulation endpoints per client IP (disabled when unset)
+            .service(
+                web::resource("/simulate")
+                    .wrap(middleware::from_fn(ratelimit::limit))
+                    .route(web::post().to(ui::simulate_handler))
+                    .route(web::get().to(ui::simulate_get_handler)),
+            )
+            .service(
+                web::resource("/simulate_sse")
+                    .wrap(middleware::from_fn(ratelimit::limit))
+                    .route(web::post().to(ui::simulate_sse_handler))
+                    .route(web::get().to(ui::simulate_sse_get_handler)),
+            )
+            .route("/equilibria", web::post().to(ui::equilibria_handler))
+            .route("/jacobian", web::post().to(ui::jacobian_handler))
+            .route("/accelerations", web::post().to(ui::accelerations_handler))
+            .route("/eigenfrequencies", web::get().to(ui::eigenfrequencies_handler))
+            .route("/effective_potential", web::post().to(ui::effective_potential_handler))
+            .route("/convergence", web::post().to(ui::convergence_handler))
+            .route("/flip_map", web::post().to(ui::flip_map_handler))
+            .route("/params", web::get().to(ui::params_handler))
+            .route("/default_scenario", web::get().to(ui::default_scenario_handler))
+            .route("/metrics", web::get().to(metrics::metrics_handler))
             .service(
-                Files::new("/", "./static")
+                Files::new("/", &static_dir)
                     .index_file("index.html")
                     .use_last_modified(true),
             )
     })
-    .bind(("0.0.0.0", port))?
+    .workers(workers)
+    .bind((host.as_str(), port))?
+    // actix handles the signals itself: SIGTERM stops accepting connections and lets in-flight
+    // requests drain for up to `shutdown_secs`; SIGINT and SIGQUIT still stop immediately.
+    // Solves past their own deadline return `timed_out`, so keep this above that limit.
+    .shutdown_timeout(shutdown_secs)
     .run()
     .await
-}
\ No newline at end of file
+}
@@ -1,85 +1,870 @@
-use nalgebra::{DMatrix, DVector};
+use nalgebra::{DMatrix, DVector, RealField};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Chains with more links than this assemble M and C in parallel (rayon).
+pub const PARALLEL_THRESHOLD: usize = 16;
+
+/// Position dependence of gravity. Points are in the pivot frame (pivot at the origin).
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GravityModel<T> {
+    /// Constant field of magnitude `g` along `g_angle`.
+    Uniform,
+    /// Attraction μ / r² towards a point mass at `center` (μ = G M_planet); `g` and
+    /// `g_angle` are ignored. Rods are lumped at their midpoints.
+    InverseSquare { center: [T; 2], mu: T },
+}
+
+/// How `set_centripetal_matrix` evaluates C. Both forms give the same vector; `Christoffel`
+/// is an independent O(n³) derivation kept as a debug option to cross-check `Direct`.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CentripetalForm {
+    /// C_i = Σ_j a_ij sin(θi − θj) ω_j² (see `centripetal_entry`).
+    #[default]
+    Direct,
+    /// C_i = Σ_jk Γ_ijk ω_j ω_k from the derivatives of M (see `christoffel_entry`).
+    Christoffel,
+}
 
 /// Solves the Lagrangian equations: M α + C + G = 0
 /// This version preserves 1-based indexing for direct mapping to physics derivations.
-pub struct NPendulumMath {
-    pub g: f64,
+/// Generic over the scalar type so the solver can also run in `f32` (see `Precision`).
+pub struct NPendulumMath<T = f64> {
+    pub g: T,             // Gravity magnitude
+    pub g_angle: T,       // Gravity direction, measured from straight down (−y) towards +x
     pub n: usize,
-    pub masses: Vec<f64>,   // [0, m1, m2, ..., mn]
-    pub lengths: Vec<f64>,  // [0, l1, l2, ..., ln]
-    pub angles: Vec<f64>,   // [0, θ1, θ2, ..., θn]
-    pub ang_vels: Vec<f64>, // [0, ω1, ω2, ..., ωn]
+    pub masses: Vec<T>,   // [0, m1, m2, ..., mn]
+    pub lengths: Vec<T>,  // [0, l1, l2, ..., ln]
+    pub angles: Vec<T>,   // [0, θ1, θ2, ..., θn]
+    pub ang_vels: Vec<T>, // [0, ω1, ω2, ..., ωn]
+    pub rod_inertia: bool, // Links are uniform rods of mass m_i instead of massless rods + bobs
+    pub gravity_enabled: bool, // When false, G is identically zero (pure-inertia motion)
+    pub gravity_model: GravityModel<T>, // Uniform (g, g_angle) or position-dependent
+    pub centripetal_form: CentripetalForm, // Formula behind `set_centripetal_matrix`
+    /// Tree topology: [0, p1, ..., pn] with p_i < i the link that link i hangs from (0 is the
+    /// pivot). `None` is the serial chain p_i = i − 1.
+    pub parents: Option<Vec<usize>>,
 }
 
-impl NPendulumMath {
-    pub fn new(n: usize, masses: Vec<f64>, lengths: Vec<f64>, angles: Vec<f64>, ang_vels: Vec<f64>) -> Self {
+impl<T: RealField + Copy> NPendulumMath<T> {
+    pub fn new(n: usize, masses: Vec<T>, lengths: Vec<T>, angles: Vec<T>, ang_vels: Vec<T>) -> Self {
         Self {
-            g: 9.81,
+            g: nalgebra::convert(9.81),
+            g_angle: T::zero(),
             n,
             masses,
             lengths,
             angles,
             ang_vels,
+            rod_inertia: false,
+            gravity_enabled: true,
+            gravity_model: GravityModel::Uniform,
+            centripetal_form: CentripetalForm::Direct,
+            parents: None,
+        }
+    }
+
+    /// Link that link `k` hangs from (0 = pivot).
+    pub fn parent(&self, k: usize) -> usize {
+        match &self.parents {
+            Some(parents) => parents[k],
+            None => k - 1,
+        }
+    }
+
+    /// True if link `i` hangs (directly or indirectly) from link `k`, or is `k` itself.
+    /// Parents always precede their children, so walking up from `i` can stop below `k`.
+    fn in_subtree(&self, k: usize, i: usize) -> bool {
+        if self.parents.is_none() {
+            return i >= k;
+        }
+        let mut link = i;
+        while link > k {
+            link = self.parent(link);
+        }
+        link == k
+    }
+
+    /// Helper to sum the masses carried by link k (k and every link below it). For the chain
+    /// this is simply m_k + ... + m_n.
+    /// Always summed in ascending index order (never split across threads) so M, C and G are
+    /// bit-stable.
+    fn mass_sum_from(&self, k: usize) -> T {
+        (k..=self.n)
+            .filter(|&i| self.in_subtree(k, i))
+            .fold(T::zero(), |acc, i| acc + self.masses[i])
+    }
+
+    /// Like `mass_sum_from`, but excluding link k's own mass.
+    fn mass_sum_below(&self, k: usize) -> T {
+        (k + 1..=self.n)
+            .filter(|&i| self.in_subtree(k, i))
+            .fold(T::zero(), |acc, i| acc + self.masses[i])
+    }
+
+    /// Effective mass multiplying l_row l_col in M_{row,col} and C_row (1-based).
+    /// Point masses: the mass carried by k = max(row, col), i.e. Σ_{i≥k} m_i on the chain;
+    /// zero on a tree when neither link hangs from the other (no bob moves with both).
+    /// Uniform rods: link k's own mass only reaches its midpoint, so it contributes m_k/2 off
+    /// the diagonal and m_k/3 on it (m_k/4 from the CM plus the rod's m_k l_k²/12 about its CM).
+    fn mass_coefficient(&self, row: usize, col: usize) -> T {
+        let k = row.max(col);
+        if !self.in_subtree(row.min(col), k) {
+            return T::zero();
+        }
+        if !self.rod_inertia {
+            return self.mass_sum_from(k);
         }
+        let own: T = if row == col { nalgebra::convert(1.0 / 3.0) } else { nalgebra::convert(0.5) };
+        self.masses[k] * own + self.mass_sum_below(k)
     }
 
-    /// Helper to sum masses from index k to n.
-    fn mass_sum_from(&self, k: usize) -> f64 {
-        self.masses[k..=self.n].iter().sum()
+    /// Effective mass multiplying g l_i in G_i: the mass carried by link i, with link i's
+    /// own mass halved for rods (its CM sits at the midpoint).
+    fn gravity_coefficient(&self, i: usize) -> T {
+        if !self.rod_inertia {
+            return self.mass_sum_from(i);
+        }
+        self.masses[i] * nalgebra::convert(0.5) + self.mass_sum_below(i)
+    }
+
+    /// Point carrying each link's own mass, [pivot, p1, ..., pn]: the bob, or the rod's
+    /// midpoint with `rod_inertia`. Accumulated from each link's parent.
+    fn mass_points(&self) -> Vec<[T; 2]> {
+        let half: T = nalgebra::convert(0.5);
+        let mut bobs = vec![[T::zero(); 2]; self.n + 1];
+        let mut points = bobs.clone();
+        for k in 1..=self.n {
+            let (sin, cos) = self.angles[k].sin_cos();
+            let [px, py] = bobs[self.parent(k)];
+            let (dx, dy) = (self.lengths[k] * sin, -self.lengths[k] * cos);
+            bobs[k] = [px + dx, py + dy];
+            points[k] = if self.rod_inertia { [px + half * dx, py + half * dy] } else { bobs[k] };
+        }
+        points
+    }
+
+    /// Gravitational acceleration at `point`: the constant (g sin φ, −g cos φ), or μ / r²
+    /// towards the center.
+    fn gravity_at(&self, point: [T; 2]) -> [T; 2] {
+        match self.gravity_model {
+            GravityModel::Uniform => {
+                let (sin, cos) = self.g_angle.sin_cos();
+                [self.g * sin, -self.g * cos]
+            }
+            GravityModel::InverseSquare { center, mu } => {
+                let (dx, dy) = (point[0] - center[0], point[1] - center[1]);
+                let r = dx.hypot(dy);
+                let s = -mu / (r * r * r);
+                [s * dx, s * dy]
+            }
+        }
+    }
+
+    /// Single mass-matrix entry M_{row,col} (1-based).
+    fn mass_entry(&self, row: usize, col: usize) -> T {
+        let m_val = self.mass_coefficient(row, col);
+        
+        m_val 
+            * self.lengths[row] 
+            * self.lengths[col] 
+            * (self.angles[row] - self.angles[col]).cos()
+    }
+
+    /// Single centripetal entry C_i (1-based), summed over j in ascending order.
+    fn centripetal_entry(&self, i: usize) -> T {
+        let mut f_term = T::zero();
+        for j in 1..=self.n {
+            let m_val = self.mass_coefficient(i, j);
+            
+            let term = m_val 
+                * self.lengths[i] 
+                * self.lengths[j] 
+                * (self.angles[i] - self.angles[j]).sin() 
+                * (self.ang_vels[j] * self.ang_vels[j]);
+            
+            f_term += term;
+        }
+        f_term
+    }
+
+    /// Single centripetal entry C_i from the Christoffel symbols of the first kind,
+    /// Γ_ijk = ½ (∂M_ij/∂θ_k + ∂M_ik/∂θ_j − ∂M_jk/∂θ_i), as C_i = Σ_jk Γ_ijk ω_j ω_k.
+    /// With M_ij = a_ij cos(θi − θj) and a_ij independent of θ,
+    /// ∂M_ij/∂θ_k = −a_ij sin(θi − θj) (δ_ik − δ_jk).
+    fn christoffel_entry(&self, i: usize) -> T {
+        let delta = |a: usize, b: usize| if a == b { T::one() } else { T::zero() };
+        let dm = |row: usize, col: usize, k: usize| {
+            let a = self.mass_coefficient(row, col) * self.lengths[row] * self.lengths[col];
+            -a * (self.angles[row] - self.angles[col]).sin() * (delta(row, k) - delta(col, k))
+        };
+        let half: T = nalgebra::convert(0.5);
+        let mut c = T::zero();
+        for j in 1..=self.n {
+            for k in 1..=self.n {
+                let gamma = half * (dm(i, j, k) + dm(i, k, j) - dm(j, k, i));
+                c += gamma * self.ang_vels[j] * self.ang_vels[k];
+            }
+        }
+        c
     }
 
     /// Computes Mass Matrix M (n x n)
-    pub fn set_mass_matrix(&self) -> DMatrix<f64> {
+    /// Above `PARALLEL_THRESHOLD` links the rows are assembled in parallel. Every entry is
+    /// still computed by the same serial expression, so the result is bit-identical.
+    pub fn set_mass_matrix(&self) -> DMatrix<T> {
         // nalgebra matrices are 0-indexed internally, so M(0,0) corresponds to your M_{1,1}
         let mut m_matrix = DMatrix::zeros(self.n, self.n);
 
+        if self.n > PARALLEL_THRESHOLD {
+            let rows: Vec<Vec<T>> = (1..=self.n)
+                .into_par_iter()
+                .map(|row| (1..=self.n).map(|col| self.mass_entry(row, col)).collect())
+                .collect();
+            for (r, values) in rows.iter().enumerate() {
+                for (c, &value) in values.iter().enumerate() {
+                    m_matrix[(r, c)] = value;
+                }
+            }
+            return m_matrix;
+        }
+
         for row in 1..=self.n {
             for col in 1..=self.n {
-                let k = row.max(col);
-                let m_val = self.mass_sum_from(k);
-                
-                let term = m_val 
-                    * self.lengths[row] 
-                    * self.lengths[col] 
-                    * (self.angles[row] - self.angles[col]).cos();
-                
-                m_matrix[(row - 1, col - 1)] = term;
+                m_matrix[(row - 1, col - 1)] = self.mass_entry(row, col);
             }
         }
         m_matrix
     }
 
-    /// Computes Centripetal Vector C (n x 1)
-    pub fn set_centripetal_matrix(&self) -> DVector<f64> {
-        let mut c_vec = DVector::zeros(self.n);
+    /// Computes Centripetal Vector C (n x 1), in the form chosen by `centripetal_form`
+    /// Parallel over entries above `PARALLEL_THRESHOLD` links (bit-identical to serial).
+    pub fn set_centripetal_matrix(&self) -> DVector<T> {
+        let entry = |i| match self.centripetal_form {
+            CentripetalForm::Direct => self.centripetal_entry(i),
+            CentripetalForm::Christoffel => self.christoffel_entry(i),
+        };
+        if self.n > PARALLEL_THRESHOLD {
+            let entries: Vec<T> = (1..=self.n).into_par_iter().map(entry).collect();
+            return DVector::from_vec(entries);
+        }
 
+        let mut c_vec = DVector::zeros(self.n);
         for i in 1..=self.n {
-            let mut f_term = 0.0;
-            for j in 1..=self.n {
-                let m_val = self.mass_sum_from(i.max(j));
-                
-                let term = m_val 
-                    * self.lengths[i] 
-                    * self.lengths[j] 
-                    * (self.angles[i] - self.angles[j]).sin() 
-                    * (self.ang_vels[j] * self.ang_vels[j]);
-                
-                f_term += term;
-            }
-            c_vec[i - 1] = f_term;
+            c_vec[i - 1] = entry(i);
         }
         c_vec
     }
 
     /// Computes Gravity Vector G (n x 1)
-    pub fn set_grav_matrix(&self) -> DVector<f64> {
+    /// For a gravity vector (gx, gy) = g (sin φ, −cos φ), the potential is
+    /// V = −Σ_i M_i l_i (gx sin θ_i − gy cos θ_i), so G_i = M_i g l_i sin(θ_i − φ).
+    /// The default φ = 0 is plain downward gravity. With `rod_inertia`, M_i uses
+    /// `gravity_coefficient`. Zero when `gravity_enabled` is false.
+    /// Position-dependent models use the general G_i = −Σ_k m_k a_k · ∂p_k/∂θ_i, with a_k the
+    /// field at link k's mass point p_k and ∂p_k/∂θ_i = l_i f_i (l_i/2 for a rod's own mass)
+    /// when k hangs from i; for a uniform field this is exactly the expression above.
+    pub fn set_grav_matrix(&self) -> DVector<T> {
         let mut g_vec = DVector::zeros(self.n);
+        if !self.gravity_enabled {
+            return g_vec;
+        }
+        if self.gravity_model != GravityModel::Uniform {
+            let half: T = nalgebra::convert(0.5);
+            let accel: Vec<[T; 2]> =
+                self.mass_points().into_iter().map(|p| self.gravity_at(p)).collect();
+            for i in 1..=self.n {
+                let (sin, cos) = self.angles[i].sin_cos();
+                g_vec[i - 1] = (i..=self.n)
+                    .filter(|&k| self.in_subtree(i, k))
+                    .fold(T::zero(), |acc, k| {
+                        let reach = if k == i && self.rod_inertia { half } else { T::one() };
+                        acc - self.masses[k] * reach * (accel[k][0] * cos + accel[k][1] * sin)
+                    })
+                    * self.lengths[i];
+            }
+            return g_vec;
+        }
 
         for i in 1..=self.n {
-            let m_val = self.mass_sum_from(i);
-            let term = m_val * self.g * self.lengths[i] * self.angles[i].sin();
+            let m_val = self.gravity_coefficient(i);
+            let term = m_val * self.g * self.lengths[i] * (self.angles[i] - self.g_angle).sin();
             g_vec[i - 1] = term;
         }
         g_vec
     }
-}
\ No newline at end of file
+
+    /// Kinetic energy T = ½ ωᵀ M ω (M from `set_mass_matrix`, so rods and trees are covered).
+    pub fn kinetic_energy(&self) -> T {
+        let omega = DVector::from_column_slice(&self.ang_vels[1..]);
+        (self.set_mass_matrix() * &omega).dot(&omega) * nalgebra::convert(0.5)
+    }
+
+    /// Potential energy V = −Σ_i M_i g l_i cos(θ_i − φ), the potential `set_grav_matrix`
+    /// differentiates (zero at the pivot's height). Zero when `gravity_enabled` is false.
+    /// For `InverseSquare`, V = Σ_k m_k μ (1/|c| − 1/|p_k − c|), also zero at the pivot.
+    pub fn potential_energy(&self) -> T {
+        if !self.gravity_enabled {
+            return T::zero();
+        }
+        if let GravityModel::InverseSquare { center, mu } = self.gravity_model {
+            let at_pivot = mu / center[0].hypot(center[1]);
+            return self.mass_points().iter().enumerate().skip(1).fold(T::zero(), |acc, (k, p)| {
+                let r = (p[0] - center[0]).hypot(p[1] - center[1]);
+                acc + self.masses[k] * (at_pivot - mu / r)
+            });
+        }
+        (1..=self.n).fold(T::zero(), |acc, i| {
+            acc - self.gravity_coefficient(i)
+                * self.g
+                * self.lengths[i]
+                * (self.angles[i] - self.g_angle).cos()
+        })
+    }
+
+    /// Total mechanical energy T + V.
+    pub fn total_energy(&self) -> T {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    /// Kapitza effective potential at the current angles for a pivot driven vertically as
+    /// y_p = a cos(Ωt). In the pivot frame the drive adds f cos(Ωt) to the generalized forces,
+    /// with f_i = M_i a Ω² l_i sin θ_i (G with g replaced by aΩ²). Averaging over the fast
+    /// drive gives V_eff = V + fᵀ M⁻¹ f / (4Ω²), valid when Ω is well above the slow motion's
+    /// frequencies. For one link: V_eff = −m g l cos θ + m a² Ω² sin² θ / 4.
+    /// `None` if the mass matrix is singular.
+    pub fn effective_potential(&self, amplitude: T, frequency: T) -> Option<T> {
+        let drive = amplitude * frequency * frequency;
+        let f = DVector::from_iterator(
+            self.n,
+            (1..=self.n).map(|i| {
+                self.gravity_coefficient(i) * drive * self.lengths[i] * self.angles[i].sin()
+            }),
+        );
+        let m_inv_f = self.set_mass_matrix().lu().solve(&f)?;
+        let four: T = nalgebra::convert(4.0);
+        Some(self.potential_energy() + f.dot(&m_inv_f) / (four * frequency * frequency))
+    }
+
+    /// Mass and stiffness matrices of the motion linearized about the hanging equilibrium
+    /// (every θ_i = φ): M₀ is M with all cosines equal to 1, and V ≈ V₀ + ½ Σ M_i g l_i δθ_i²
+    /// makes K = diag(M_i g l_i). Independent of the current state.
+    pub fn small_oscillation_matrices(&self) -> (DMatrix<T>, DMatrix<T>) {
+        let n = self.n;
+        let mass = DMatrix::from_fn(n, n, |r, c| {
+            self.mass_coefficient(r + 1, c + 1) * self.lengths[r + 1] * self.lengths[c + 1]
+        });
+        let g = if self.gravity_enabled { self.g } else { T::zero() };
+        let stiffness = DMatrix::from_diagonal(&DVector::from_fn(n, |i, _| {
+            self.gravity_coefficient(i + 1) * g * self.lengths[i + 1]
+        }));
+        (mass, stiffness)
+    }
+
+    /// Normal-mode angular frequencies (rad/s) about the hanging equilibrium, ascending: the
+    /// ω with K v = ω² M₀ v (see `small_oscillation_matrices`). `None` if M₀ is not positive
+    /// definite.
+    pub fn normal_mode_frequencies(&self) -> Option<Vec<T>> {
+        Some(self.normal_modes()?.into_iter().map(|(frequency, _)| frequency).collect())
+    }
+
+    /// Normal modes (ω, v) about the hanging equilibrium, ascending in ω. With M₀ = L Lᵀ the
+    /// problem K v = ω² M₀ v is the symmetric (L⁻¹ K L⁻ᵀ) w = ω² w, and v = L⁻ᵀ w. Each shape
+    /// v is scaled so its largest-magnitude angle is +1. `None` if M₀ is not positive definite.
+    pub fn normal_modes(&self) -> Option<Vec<(T, DVector<T>)>> {
+        let (mass, stiffness) = self.small_oscillation_matrices();
+        let l = mass.cholesky()?.l();
+        let l_inv_k = l.solve_lower_triangular(&stiffness)?;
+        let reduced = l.solve_lower_triangular(&l_inv_k.transpose())?;
+        let eigen = reduced.symmetric_eigen();
+        let l_t = l.transpose();
+        let mut modes = Vec::with_capacity(self.n);
+        for (k, &w2) in eigen.eigenvalues.iter().enumerate() {
+            let mut shape = l_t.solve_upper_triangular(&eigen.eigenvectors.column(k).into_owned())?;
+            let peak = shape.iter().fold(T::zero(), |a, &b| if b.abs() > a.abs() { b } else { a });
+            shape /= peak;
+            modes.push((w2.max(T::zero()).sqrt(), shape));
+        }
+        modes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        Some(modes)
+    }
+
+    /// Total angular momentum about the pivot: L = Σ m_i (x_i ẏ_i − y_i ẋ_i).
+    /// Bob positions and velocities are accumulated link by link from each link's parent
+    /// (on the chain x_i = Σ_{k≤i} l_k sin θ_k, y_i = −Σ_{k≤i} l_k cos θ_k).
+    /// With `rod_inertia` each rod contributes its midpoint's orbital term plus m_i l_i² ω_i / 12.
+    pub fn angular_momentum(&self) -> T {
+        // [x, y, ẋ, ẏ] of every bob, index 0 being the fixed pivot
+        let mut bobs = vec![[T::zero(); 4]; self.n + 1];
+        let mut l_total = T::zero();
+        let half: T = nalgebra::convert(0.5);
+        let twelfth: T = nalgebra::convert(1.0 / 12.0);
+
+        for i in 1..=self.n {
+            let (sin, cos) = self.angles[i].sin_cos();
+            let l = self.lengths[i];
+            let w = self.ang_vels[i];
+            let [mut x, mut y, mut vx, mut vy] = bobs[self.parent(i)];
+
+            if self.rod_inertia {
+                let (cx, cy) = (x + half * l * sin, y - half * l * cos);
+                let (cvx, cvy) = (vx + half * l * w * cos, vy + half * l * w * sin);
+                l_total += self.masses[i] * (cx * cvy - cy * cvx + twelfth * l * l * w);
+            }
+
+            x += l * sin;
+            y -= l * cos;
+            vx += l * w * cos;
+            vy += l * w * sin;
+
+            if !self.rod_inertia {
+                l_total += self.masses[i] * (x * vy - y * vx);
+            }
+            bobs[i] = [x, y, vx, vy];
+        }
+        l_total
+    }
+
+    /// Axial force in each link for the given angular accelerations `alphas` = [0, α1..αn]
+    /// (positive = tension, negative = compression): [0, T1, ..., Tn].
+    /// Newton on everything hanging from link k: the link pulls its bob towards the parent
+    /// with −T_k e_k, so T_k = −e_k · Σ_{i below k} m_i (a_i − g), e_k = (sin θ_k, −cos θ_k).
+    /// Bob accelerations accumulate from the parent: a_i = a_p + l_i (α_i f_i − ω_i² e_i),
+    /// f_i = (cos θ_i, sin θ_i). With `rod_inertia`, a_i is the rod midpoint's acceleration and
+    /// T_k is the force at the top joint. g is taken at each mass point (see `gravity_at`).
+    pub fn tensions(&self, alphas: &[T]) -> Vec<T> {
+        let half: T = nalgebra::convert(0.5);
+        let points = match self.gravity_model {
+            GravityModel::Uniform => None,
+            GravityModel::InverseSquare { .. } => Some(self.mass_points()),
+        };
+
+        // Acceleration of every bob (index 0 is the fixed pivot) and of each link's mass point
+        let mut bob_acc = vec![[T::zero(); 2]; self.n + 1];
+        let mut forces = vec![[T::zero(); 2]; self.n + 1];
+        for i in 1..=self.n {
+            let (sin, cos) = self.angles[i].sin_cos();
+            let (l, w, a) = (self.lengths[i], self.ang_vels[i], alphas[i]);
+            let rel = [l * (a * cos - w * w * sin), l * (a * sin + w * w * cos)];
+            let [ax, ay] = bob_acc[self.parent(i)];
+            bob_acc[i] = [ax + rel[0], ay + rel[1]];
+
+            let [px, py] = if self.rod_inertia {
+                [ax + half * rel[0], ay + half * rel[1]]
+            } else {
+                bob_acc[i]
+            };
+            let [gx, gy] = match (self.gravity_enabled, &points) {
+                (false, _) => [T::zero(); 2],
+                (true, None) => self.gravity_at([T::zero(); 2]),
+                (true, Some(points)) => self.gravity_at(points[i]),
+            };
+            forces[i] = [self.masses[i] * (px - gx), self.masses[i] * (py - gy)];
+        }
+
+        // Children always follow their parents, so a descending sweep accumulates subtrees
+        let mut tensions = vec![T::zero(); self.n + 1];
+        for k in (1..=self.n).rev() {
+            let [fx, fy] = forces[k];
+            let (sin, cos) = self.angles[k].sin_cos();
+            tensions[k] = -(sin * fx - cos * fy);
+            let p = self.parent(k);
+            if p > 0 {
+                forces[p] = [forces[p][0] + fx, forces[p][1] + fy];
+            }
+        }
+        tensions
+    }
+}
+
+/// Elastic-link variant: each link is a spring with stiffness k_i and rest length L_i, so the
+/// generalized coordinates gain radial DOFs: q = [θ1..θn, r1..rn] (r_i = current link length).
+/// Bob i sits at p_i = Σ_{k≤i} r_k e_k with e_k = (sin θ_k, −cos θ_k), f_k = (cos θ_k, sin θ_k).
+/// The equations are assembled from the bob-space Jacobian J = ∂p/∂q:
+///   (Jᵀ m J) q̈ = Jᵀ (m g − m J̇q̇) − ∂V_spring/∂q
+/// Same 1-based indexing as `NPendulumMath`.
+pub struct SpringPendulumMath {
+    pub gx: f64,
+    pub gy: f64,
+    pub n: usize,
+    pub masses: Vec<f64>,       // [0, m1, ..., mn]
+    pub rest_lengths: Vec<f64>, // [0, L1, ..., Ln]
+    pub stiffness: Vec<f64>,    // [0, k1, ..., kn]
+    pub angles: Vec<f64>,       // [0, θ1, ..., θn]
+    pub radii: Vec<f64>,        // [0, r1, ..., rn]
+    pub ang_vels: Vec<f64>,     // [0, ω1, ..., ωn]
+    pub radial_vels: Vec<f64>,  // [0, ṙ1, ..., ṙn]
+}
+
+impl SpringPendulumMath {
+    /// Radial (e_k) and tangential (f_k) unit vectors of link k.
+    fn unit_vectors(&self, k: usize) -> ([f64; 2], [f64; 2]) {
+        let (sin, cos) = self.angles[k].sin_cos();
+        ([sin, -cos], [cos, sin])
+    }
+
+    /// Jacobian J = ∂p/∂q (2n x 2n). Rows are [x1, y1, x2, y2, ...],
+    /// columns are [θ1..θn, r1..rn].
+    pub fn jacobian(&self) -> DMatrix<f64> {
+        let n = self.n;
+        let mut jac = DMatrix::zeros(2 * n, 2 * n);
+
+        for k in 1..=n {
+            let (e, f) = self.unit_vectors(k);
+            // Link k moves every bob from k outwards
+            for i in k..=n {
+                for axis in 0..2 {
+                    jac[(2 * (i - 1) + axis, k - 1)] = self.radii[k] * f[axis];
+                    jac[(2 * (i - 1) + axis, n + k - 1)] = e[axis];
+                }
+            }
+        }
+        jac
+    }
+
+    /// Velocity-product part of the bob accelerations, J̇q̇ (2n x 1):
+    /// Σ_{k≤i} (2 ṙ_k ω_k f_k − r_k ω_k² e_k) for each bob i.
+    pub fn velocity_product_accel(&self) -> DVector<f64> {
+        let mut acc = DVector::zeros(2 * self.n);
+        let (mut ax, mut ay) = (0.0, 0.0);
+
+        for i in 1..=self.n {
+            let (e, f) = self.unit_vectors(i);
+            let w = self.ang_vels[i];
+            let coriolis = 2.0 * self.radial_vels[i] * w;
+            let centripetal = self.radii[i] * w * w;
+            ax += coriolis * f[0] - centripetal * e[0];
+            ay += coriolis * f[1] - centripetal * e[1];
+            acc[2 * (i - 1)] = ax;
+            acc[2 * (i - 1) + 1] = ay;
+        }
+        acc
+    }
+
+    /// Bob velocities ṗ = J q̇, laid out as [ẋ1, ẏ1, ẋ2, ẏ2, ...].
+    pub fn bob_velocities(&self) -> DVector<f64> {
+        let n = self.n;
+        let q_dot = DVector::from_iterator(
+            2 * n,
+            self.ang_vels[1..=n].iter().chain(&self.radial_vels[1..=n]).copied(),
+        );
+        self.jacobian() * q_dot
+    }
+
+    /// Bob positions [x1, y1, x2, y2, ...] with the pivot at the origin.
+    pub fn bob_positions(&self) -> Vec<f64> {
+        let (mut x, mut y) = (0.0, 0.0);
+        let mut coords = Vec::with_capacity(2 * self.n);
+        for k in 1..=self.n {
+            let (e, _) = self.unit_vectors(k);
+            x += self.radii[k] * e[0];
+            y += self.radii[k] * e[1];
+            coords.push(x);
+            coords.push(y);
+        }
+        coords
+    }
+
+    /// Generalized mass matrix Jᵀ m J and right-hand side Jᵀ (m g − m J̇q̇) − ∂V_spring/∂q.
+    pub fn equations(&self) -> (DMatrix<f64>, DVector<f64>) {
+        let n = self.n;
+        let jac = self.jacobian();
+        let vp = self.velocity_product_accel();
+
+        let mut weighted = jac.clone(); // m J
+        let mut forces = DVector::zeros(2 * n); // m g − m J̇q̇
+        for i in 1..=n {
+            let m = self.masses[i];
+            let (row_x, row_y) = (2 * (i - 1), 2 * (i - 1) + 1);
+            weighted.row_mut(row_x).scale_mut(m);
+            weighted.row_mut(row_y).scale_mut(m);
+            forces[row_x] = m * (self.gx - vp[row_x]);
+            forces[row_y] = m * (self.gy - vp[row_y]);
+        }
+
+        let mass = jac.transpose() * weighted;
+        let mut rhs = jac.transpose() * forces;
+        for k in 1..=n {
+            rhs[n + k - 1] -= self.stiffness[k] * (self.radii[k] - self.rest_lengths[k]);
+        }
+        (mass, rhs)
+    }
+
+    /// Total mechanical energy: kinetic + gravitational + elastic.
+    pub fn total_energy(&self) -> f64 {
+        let vel = self.bob_velocities();
+        let pos = self.bob_positions();
+        let mut energy = 0.0;
+        for i in 1..=self.n {
+            let (ix, iy) = (2 * (i - 1), 2 * (i - 1) + 1);
+            let m = self.masses[i];
+            energy += 0.5 * m * (vel[ix] * vel[ix] + vel[iy] * vel[iy]);
+            energy -= m * (self.gx * pos[ix] + self.gy * pos[iy]);
+            let stretch = self.radii[i] - self.rest_lengths[i];
+            energy += 0.5 * self.stiffness[i] * stretch * stretch;
+        }
+        energy
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOL: f64 = 1e-12;
+
+    /// Double pendulum with m = (1, 2), l = (1, 0.5) at the given state.
+    fn double(angles: [f64; 2], ang_vels: [f64; 2]) -> NPendulumMath {
+        NPendulumMath::new(
+            2,
+            vec![0.0, 1.0, 2.0],
+            vec![0.0, 1.0, 0.5],
+            vec![0.0, angles[0], angles[1]],
+            vec![0.0, ang_vels[0], ang_vels[1]],
+        )
+    }
+
+    #[test]
+    fn downward_gravity_vector_reproduces_the_scalar_form_exactly() {
+        // (gx, gy) = (0, −9.81) as `NPendulumSolver::with_gravity` converts it
+        let (gx, gy) = (0.0_f64, -9.81_f64);
+        let (masses, lengths) = (vec![0.0, 1.5, 0.7, 2.0], vec![0.0, 1.0, 0.8, 1.3]);
+        for angles in [[0.3, -1.1, 2.4], [0.0, 3.0, -3.0], [-2.2, 0.01, 1.57]] {
+            let mut math = NPendulumMath::new(
+                3,
+                masses.clone(),
+                lengths.clone(),
+                vec![0.0, angles[0], angles[1], angles[2]],
+                vec![0.0; 4],
+            );
+            math.g = gx.hypot(gy);
+            math.g_angle = gx.atan2(-gy);
+            // Before the gravity vector: G_i = (Σ_{k≥i} m_k) g l_i sin θ_i
+            let baseline: Vec<f64> = (1..=3)
+                .map(|i| {
+                    let below = (i..=3).fold(0.0, |acc, k| acc + masses[k]);
+                    below * 9.81 * lengths[i] * angles[i - 1].sin()
+                })
+                .collect();
+            assert_eq!(math.set_grav_matrix().as_slice(), baseline.as_slice(), "{angles:?}");
+        }
+    }
+
+    #[test]
+    fn double_pendulum_mass_matrix_matches_hand_derivation() {
+        // M = [[(m1 + m2) l1², m2 l1 l2 cos(θ1 − θ2)], [m2 l1 l2 cos(θ1 − θ2), m2 l2²]]
+        let m = double([1.2, -0.5], [0.0, 0.0]).set_mass_matrix();
+        let coupling = 2.0 * 1.0 * 0.5 * (1.7_f64).cos();
+        assert!((m[(0, 0)] - 3.0).abs() < TOL);
+        assert!((m[(0, 1)] - coupling).abs() < TOL);
+        assert!((m[(1, 0)] - coupling).abs() < TOL);
+        assert!((m[(1, 1)] - 0.5).abs() < TOL);
+    }
+
+    #[test]
+    fn triple_pendulum_mass_matrix_matches_hand_derivation() {
+        // Chain: M_ij = (Σ_{k ≥ max(i, j)} m_k) l_i l_j cos(θi − θj)
+        let (masses, lengths) = ([0.0, 1.0, 2.0, 3.0], [0.0, 1.0, 0.5, 2.0]);
+        let angles = [0.0, 0.3, -1.1, 2.0];
+        let mut math =
+            NPendulumMath::new(3, masses.to_vec(), lengths.to_vec(), angles.to_vec(), vec![0.0; 4]);
+        let entry = |mass: f64, i: usize, j: usize| {
+            mass * lengths[i] * lengths[j] * (angles[i] - angles[j]).cos()
+        };
+        let expected = [
+            [entry(6.0, 1, 1), entry(5.0, 1, 2), entry(3.0, 1, 3)],
+            [entry(5.0, 2, 1), entry(5.0, 2, 2), entry(3.0, 2, 3)],
+            [entry(3.0, 3, 1), entry(3.0, 3, 2), entry(3.0, 3, 3)],
+        ];
+        let m = math.set_mass_matrix();
+        for (i, row) in expected.iter().enumerate() {
+            for (j, &value) in row.iter().enumerate() {
+                assert!((m[(i, j)] - value).abs() < TOL, "M{}{}", i + 1, j + 1);
+            }
+        }
+
+        // Links 2 and 3 both hang from link 1: they share no bob, so M23 = 0
+        math.parents = Some(vec![0, 0, 1, 1]);
+        let m = math.set_mass_matrix();
+        assert!((m[(0, 0)] - entry(6.0, 1, 1)).abs() < TOL);
+        assert!((m[(0, 1)] - entry(2.0, 1, 2)).abs() < TOL);
+        assert!((m[(0, 2)] - entry(3.0, 1, 3)).abs() < TOL);
+        assert!((m[(1, 1)] - entry(2.0, 2, 2)).abs() < TOL);
+        assert_eq!(m[(1, 2)], 0.0);
+        assert_eq!(m[(2, 1)], 0.0);
+    }
+
+    proptest::proptest! {
+        /// M = Jᵀ diag(m) J for the bob Jacobian J, so it is symmetric and positive-definite
+        /// for any positive masses and lengths, whatever the angles.
+        #[test]
+        fn mass_matrix_is_symmetric_positive_definite(
+            // (mass, length, angle) per link
+            links in proptest::collection::vec((0.1..10.0, 0.1..5.0, -10.0..10.0_f64), 1..8),
+            rod_inertia: bool,
+        ) {
+            let n = links.len();
+            let one_based = |f: fn(&(f64, f64, f64)) -> f64| {
+                std::iter::once(0.0).chain(links.iter().map(f)).collect::<Vec<_>>()
+            };
+            let mut math = NPendulumMath::new(
+                n,
+                one_based(|l| l.0),
+                one_based(|l| l.1),
+                one_based(|l| l.2),
+                vec![0.0; n + 1],
+            );
+            math.rod_inertia = rod_inertia;
+            let m = math.set_mass_matrix();
+            for i in 0..n {
+                for j in 0..i {
+                    let scale = m[(i, i)].abs().max(m[(j, j)].abs());
+                    proptest::prop_assert!((m[(i, j)] - m[(j, i)]).abs() <= 1e-12 * scale);
+                }
+            }
+            proptest::prop_assert!(m.clone().cholesky().is_some(), "not positive-definite: {}", m);
+        }
+
+        /// The Christoffel-symbol form is derived independently of `centripetal_entry`, so
+        /// agreement checks the signs and indices of the closed form, chains and trees alike.
+        #[test]
+        fn centripetal_forms_agree(
+            // (mass, length, angle, angular velocity, parent pick) per link
+            links in proptest::collection::vec(
+                (0.1..10.0, 0.1..5.0, -10.0..10.0, -20.0..20.0, 0.0..1.0_f64),
+                1..8,
+            ),
+            rod_inertia: bool,
+            tree: bool,
+        ) {
+            let n = links.len();
+            let one_based = |f: fn(&(f64, f64, f64, f64, f64)) -> f64| {
+                std::iter::once(0.0).chain(links.iter().map(f)).collect::<Vec<_>>()
+            };
+            let mut math = NPendulumMath::new(
+                n,
+                one_based(|l| l.0),
+                one_based(|l| l.1),
+                one_based(|l| l.2),
+                one_based(|l| l.3),
+            );
+            math.rod_inertia = rod_inertia;
+            if tree {
+                // Link i hangs from any of the links above it (or the pivot)
+                let picks = one_based(|l| l.4);
+                math.parents = Some((0..=n).map(|i| (picks[i] * i as f64) as usize).collect());
+            }
+            let direct = math.set_centripetal_matrix();
+            math.centripetal_form = CentripetalForm::Christoffel;
+            let christoffel = math.set_centripetal_matrix();
+            let scale = direct.amax().max(1.0);
+            proptest::prop_assert!(
+                (&direct - &christoffel).amax() <= 1e-10 * scale,
+                "direct {} vs christoffel {}",
+                direct,
+                christoffel
+            );
+        }
+    }
+
+    #[test]
+    fn double_pendulum_force_terms_match_hand_derivation() {
+        let math = double([1.2, -0.5], [0.7, -1.3]);
+        let (g, d) = (9.81, 1.7_f64);
+
+        // C1 = m2 l1 l2 sin(θ1 − θ2) ω2², C2 = −m2 l1 l2 sin(θ1 − θ2) ω1²
+        let c = math.set_centripetal_matrix();
+        assert!((c[0] - 2.0 * 0.5 * d.sin() * 1.69).abs() < TOL);
+        assert!((c[1] + 2.0 * 0.5 * d.sin() * 0.49).abs() < TOL);
+
+        // G1 = (m1 + m2) g l1 sin θ1, G2 = m2 g l2 sin θ2
+        let grav = math.set_grav_matrix();
+        assert!((grav[0] - 3.0 * g * (1.2_f64).sin()).abs() < TOL);
+        assert!((grav[1] - 2.0 * g * 0.5 * (-0.5_f64).sin()).abs() < TOL);
+    }
+
+    #[test]
+    fn single_pendulum_angular_momentum_is_m_l_squared_omega() {
+        for (theta, omega) in [(0.0, 1.5_f64), (0.7, -2.0), (2.9, 0.3)] {
+            let (angles, ang_vels) = (vec![0.0, theta], vec![0.0, omega]);
+            let math = NPendulumMath::new(1, vec![0.0, 3.0], vec![0.0, 2.0], angles, ang_vels);
+            assert!((math.angular_momentum() - 3.0 * 2.0 * 2.0 * omega).abs() < TOL);
+        }
+    }
+
+    #[test]
+    fn single_pendulum_normal_mode_is_sqrt_g_over_l() {
+        let math =
+            NPendulumMath::new(1, vec![0.0, 3.0], vec![0.0, 2.0], vec![0.0; 2], vec![0.0; 2]);
+        let frequencies = math.normal_mode_frequencies().unwrap();
+        assert_eq!(frequencies.len(), 1);
+        assert!((frequencies[0] - (9.81_f64 / 2.0).sqrt()).abs() < TOL);
+    }
+
+    #[test]
+    fn equal_double_pendulum_normal_modes_match_textbook() {
+        // m1 = m2, l1 = l2 = l: ω² = (2 ∓ √2) g / l
+        let (ones, zeros) = (vec![0.0, 1.0, 1.0], vec![0.0; 3]);
+        let math = NPendulumMath::new(2, ones.clone(), ones, zeros.clone(), zeros);
+        let frequencies = math.normal_mode_frequencies().unwrap();
+        let root2 = 2.0_f64.sqrt();
+        assert!((frequencies[0] - ((2.0 - root2) * 9.81).sqrt()).abs() < TOL);
+        assert!((frequencies[1] - ((2.0 + root2) * 9.81).sqrt()).abs() < TOL);
+    }
+
+    #[test]
+    fn equal_double_pendulum_mode_shapes_match_textbook() {
+        // In phase θ2 = √2 θ1 for the slow mode, out of phase θ2 = −√2 θ1 for the fast one
+        let (ones, zeros) = (vec![0.0, 1.0, 1.0], vec![0.0; 3]);
+        let math = NPendulumMath::new(2, ones.clone(), ones, zeros.clone(), zeros);
+        let modes = math.normal_modes().unwrap();
+        let half_root2 = 0.5 * 2.0_f64.sqrt();
+        assert!((modes[0].1[0] - half_root2).abs() < TOL && (modes[0].1[1] - 1.0).abs() < TOL);
+        assert!((modes[1].1[0] + half_root2).abs() < TOL && (modes[1].1[1] - 1.0).abs() < TOL);
+    }
+
+    #[test]
+    fn inverse_square_gravity_is_the_gradient_of_its_potential() {
+        const H: f64 = 1e-6;
+        for rod_inertia in [false, true] {
+            let mut math = double([1.2, -0.5], [0.0, 0.0]);
+            math.rod_inertia = rod_inertia;
+            math.gravity_model = GravityModel::InverseSquare { center: [0.3, -4.0], mu: 160.0 };
+            let grav = math.set_grav_matrix();
+            for i in 1..=2 {
+                let mut shifted = |d: f64| {
+                    math.angles[i] += d;
+                    let v = math.potential_energy();
+                    math.angles[i] -= d;
+                    v
+                };
+                let slope = (shifted(H) - shifted(-H)) / (2.0 * H);
+                assert!((grav[i - 1] - slope).abs() < 1e-6, "G{i}: {} vs {slope}", grav[i - 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn far_planet_approaches_uniform_gravity() {
+        let uniform = double([1.2, -0.5], [0.0, 0.0]);
+        let mut planet = double([1.2, -0.5], [0.0, 0.0]);
+        let radius = 1e6;
+        planet.gravity_model =
+            GravityModel::InverseSquare { center: [0.0, -radius], mu: 9.81 * radius * radius };
+        let (g_uniform, g_planet) = (uniform.set_grav_matrix(), planet.set_grav_matrix());
+        assert!((g_uniform - g_planet).norm() < 1e-4);
+        let tensions = planet.tensions(&[0.0; 3]);
+        assert!((tensions[2] - uniform.tensions(&[0.0; 3])[2]).abs() < 1e-4);
+    }
+
+    #[test]
+    fn hanging_chain_tension_carries_the_weight_below() {
+        let math = double([0.0, 0.0], [0.0, 0.0]);
+        let tensions = math.tensions(&[0.0; 3]);
+        assert!((tensions[1] - 3.0 * 9.81).abs() < TOL);
+        assert!((tensions[2] - 2.0 * 9.81).abs() < TOL);
+    }
+}
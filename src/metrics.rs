@@ -0,0 +1,102 @@
+// src/metrics.rs
+use actix_web::{web, HttpResponse, Result};
+use prometheus::{Encoder, Histogram, HistogramOpts, IntCounter, Registry, TextEncoder};
+use std::time::Duration;
+
+/// Upper bucket bounds for the chain size histogram (`n` is capped at `MAX_N` = 150).
+const N_BUCKETS: [f64; 9] = [1.0, 2.0, 3.0, 5.0, 10.0, 20.0, 50.0, 100.0, 150.0];
+
+/// Prometheus metrics for `/simulate`, served as text at `/metrics`.
+/// Built once in `main` and shared by every worker as app data.
+pub struct Metrics {
+    registry: Registry,
+    simulations: IntCounter,
+    failed: IntCounter,
+    solve_seconds: Histogram,
+    links: Histogram,
+}
+
+impl Metrics {
+    /// Registers all metrics in a fresh registry (names are fixed, so this cannot clash).
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let simulations =
+            IntCounter::new("simulations_total", "Simulation requests handled").unwrap();
+        let failed = IntCounter::new(
+            "simulations_failed_total",
+            "Simulation requests that ended in an error (including timeouts)",
+        )
+        .unwrap();
+        let solve_seconds = Histogram::with_opts(HistogramOpts::new(
+            "simulation_duration_seconds",
+            "Wall-clock time spent solving a simulation request",
+        ))
+        .unwrap();
+        let links = Histogram::with_opts(
+            HistogramOpts::new("simulation_links", "Number of links per simulation request")
+                .buckets(N_BUCKETS.to_vec()),
+        )
+        .unwrap();
+        for metric in [&simulations, &failed] {
+            registry.register(Box::new(metric.clone())).unwrap();
+        }
+        for metric in [&solve_seconds, &links] {
+            registry.register(Box::new(metric.clone())).unwrap();
+        }
+        Metrics { registry, simulations, failed, solve_seconds, links }
+    }
+
+    /// Records one finished simulation request. `n` is 0 when it failed before the chain
+    /// size was known, in which case it is left out of the size histogram.
+    pub fn observe_simulation(&self, n: usize, elapsed: Duration, success: bool) {
+        self.simulations.inc();
+        if !success {
+            self.failed.inc();
+        }
+        self.solve_seconds.observe(elapsed.as_secs_f64());
+        if n > 0 {
+            self.links.observe(n as f64);
+        }
+    }
+
+    /// Current values in the Prometheus text exposition format.
+    fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding of gathered metrics");
+        String::from_utf8(buffer).expect("the text format is UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Metrics Handler: exposes the counters and histograms for Prometheus to scrape.
+pub async fn metrics_handler(metrics: web::Data<Metrics>) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok()
+        .content_type(TextEncoder::new().format_type())
+        .body(metrics.encode()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn observations_show_up_in_the_exposition() {
+        let metrics = Metrics::new();
+        metrics.observe_simulation(2, Duration::from_millis(30), true);
+        metrics.observe_simulation(0, Duration::from_millis(1), false);
+
+        let text = metrics.encode();
+        assert!(text.contains("simulations_total 2"), "{text}");
+        assert!(text.contains("simulations_failed_total 1"), "{text}");
+        assert!(text.contains("simulation_duration_seconds_count 2"), "{text}");
+        assert!(text.contains("simulation_links_bucket{le=\"2\"} 1"), "{text}");
+        assert!(text.contains("simulation_links_count 1"), "{text}");
+    }
+}
@@ -0,0 +1,117 @@
+// src/ratelimit.rs
+use crate::error::SimError;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpResponse};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Tracked clients above which idle buckets are dropped. A bucket that has refilled is
+/// indistinguishable from a fresh one, so pruning it changes nothing for that client.
+const PRUNE_AT: usize = 10_000;
+
+/// Token bucket per client IP: each request takes a token, and tokens come back at `rate`
+/// per second up to `burst`. Built once in `main` and shared by every worker as app data;
+/// a rate of 0 disables it.
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: f64) -> Self {
+        RateLimiter { rate, burst, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Takes a token for `ip` at `now`, or returns how long until the next one is available.
+    fn acquire(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        if self.rate == 0.0 {
+            return Ok(());
+        }
+        // A panic while holding the lock leaves at worst one stale bucket, so keep using it
+        let mut buckets = self.buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let refilled = |bucket: &Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+            (bucket.tokens + elapsed * self.rate).min(self.burst)
+        };
+        if buckets.len() >= PRUNE_AT {
+            buckets.retain(|_, bucket| refilled(bucket) < self.burst);
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket { tokens: self.burst, updated: now });
+        bucket.tokens = refilled(bucket);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+}
+
+/// Middleware (see `main`): answers 429 with a `Retry-After` header and the usual error
+/// envelope once the peer IP has used up its bucket. Keyed on the connection's peer address,
+/// so behind a reverse proxy every client shares the proxy's bucket.
+pub async fn limit<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let limiter = req.app_data::<web::Data<RateLimiter>>();
+    let verdict = match (limiter, req.peer_addr()) {
+        (Some(limiter), Some(peer)) => limiter.acquire(peer.ip(), Instant::now()),
+        _ => Ok(()),
+    };
+    if let Err(wait) = verdict {
+        let retry_after_secs = wait.as_secs_f64().ceil().max(1.0) as u64;
+        tracing::warn!(peer = ?req.peer_addr(), retry_after_secs, "rate limited");
+        let error = SimError::RateLimited { retry_after_secs };
+        let response = HttpResponse::TooManyRequests()
+            .insert_header((header::RETRY_AFTER, retry_after_secs))
+            .json(serde_json::json!({ "success": false, "error": error }));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_a_burst_then_refills_at_the_rate() {
+        let limiter = RateLimiter::new(2.0, 3.0);
+        let (client, other) = ([10, 0, 0, 1].into(), [10, 0, 0, 2].into());
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.acquire(client, start).is_ok());
+        }
+        let wait = limiter.acquire(client, start).unwrap_err();
+        assert!((wait.as_secs_f64() - 0.5).abs() < 1e-9, "{wait:?}");
+        // Another client has its own bucket
+        assert!(limiter.acquire(other, start).is_ok());
+        // Half a second brings back one token at 2/s
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.acquire(client, later).is_ok());
+        assert!(limiter.acquire(client, later).is_err());
+    }
+
+    #[test]
+    fn zero_rate_disables_the_limit() {
+        let limiter = RateLimiter::new(0.0, 1.0);
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(limiter.acquire([127, 0, 0, 1].into(), now).is_ok());
+        }
+    }
+}
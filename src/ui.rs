@@ -1,50 +1,683 @@
 // src/ui.rs
-use crate::logic::NPendulumSolver;
-use actix_web::{web, HttpResponse, Result};
+// Without the `server` feature only `simulate_json` (the WebAssembly entry point) is reachable,
+// and the other endpoints' compute functions are unused
+#![cfg_attr(not(feature = "server"), allow(dead_code))]
+
+#[cfg(feature = "server")]
+use crate::cache::{CachedBody, ResponseCache};
+use crate::error::SimError;
+#[cfg(feature = "server")]
+use crate::metrics::Metrics;
+use crate::logic::{
+    self, CollisionCheck, CrossingDirection, Damping, DampingModel, Deadline, NPendulumSolver,
+    Precision, Quadrature, SolveOptions, SpringPendulumSolver, Trajectory,
+};
+use crate::math::{CentripetalForm, GravityModel};
+#[cfg(feature = "server")]
+use actix_web::error::{ErrorInternalServerError, InternalError, JsonPayloadError};
+#[cfg(feature = "server")]
+use actix_web::http::header::{self, ContentEncoding};
+#[cfg(feature = "server")]
+use actix_web::http::StatusCode;
+#[cfg(feature = "server")]
+use actix_web::{rt, web, HttpRequest, HttpResponse, Result};
+#[cfg(feature = "server")]
+use futures_util::stream;
 use serde::{Deserialize, Serialize};
 use nalgebra::DVector;
+use rayon::prelude::*;
+#[cfg(feature = "server")]
+use std::fs;
+use std::ops::ControlFlow;
+#[cfg(feature = "server")]
+use std::path::Path;
+#[cfg(feature = "server")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "server")]
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(feature = "server")]
+use tokio::sync::mpsc;
+#[cfg(feature = "server")]
+use tracing::Instrument;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct SimParams {
+    #[serde(default)]
+    preset: Option<String>,  // Named setup filling in any of the four fields below left empty
+    #[serde(default)]
     n: usize,                // Number of pendulums
+    #[serde(default)]
     masses: String,          // Comma-separated masses
+    #[serde(default)]
     lengths: String,         // Comma-separated lengths
-    initial_angles: String,  // Comma-separated initial angles (degrees)
-    t_max: f64,              // Simulation duration
-    n_points: usize,         // Resolution
+    #[serde(default)]
+    normalize_total_length: Option<f64>, // Scale `lengths` so they sum to this (m)
+    #[serde(default)]
+    initial_angles: String,  // Comma-separated initial angles (`angle_unit`), or e.g. "horizontal"
+    t_max: f64,              // Simulation duration (s); negative integrates backward in time
+    #[serde(default)]
+    n_points: usize,         // Resolution (ignored when `dt` is given)
+    #[serde(default)]
+    dt: Option<f64>,         // Time step (s); derives n_points = ceil(t_max / dt) + 1
+    #[serde(default = "default_stride")]
+    output_stride: usize,    // Record every Nth integration step
+    #[serde(default = "default_substeps")]
+    substeps: usize,         // RK4 substeps per integration step (accuracy only)
+    #[serde(default)]
+    resume_state: Option<Vec<f64>>, // Full [θ1..θn, ω1..ωn] state (radians) to continue from
+    #[serde(default)]
+    poincare_section: Option<PoincareParams>, // Record a Poincaré section alongside the trajectory
+    #[serde(default)]
+    lyapunov_epsilon: Option<f64>, // θ1 perturbation for the Lyapunov exponent estimate
+    #[serde(default)]
+    divergence_epsilon: Option<f64>, // θ1 perturbation of a companion run to compare against
+    #[serde(default)]
+    reversibility_check: bool, // Integrate back from the final state and report the residual
+    #[serde(default)]
+    precision: Precision,      // "f64" (default) or "f32" for low-precision previews
+    #[serde(default)]
+    min_separation: Option<f64>, // Report (or stop at) the first time two bobs get this close
+    #[serde(default)]
+    stop_on_collision: bool,     // Truncate the trajectory at the first collision
+    #[serde(default)]
+    sample_times: Option<Vec<f64>>, // Extra times (0 to t_max) to report interpolated positions at
+    #[serde(default)]
+    fps: Option<f64>,        // Output frames exactly 1/fps s apart, interpolated (video export)
+    #[serde(default)]
+    gx: f64,                     // Gravity x-component (m/s²)
+    #[serde(default = "default_gy")]
+    gy: f64,                     // Gravity y-component (m/s², default -9.81 = straight down)
+    #[serde(default)]
+    pivot_x: f64,                // Pivot x in output coordinates (m); physics is unaffected
+    #[serde(default)]
+    pivot_y: f64,                // Pivot y in output coordinates (m)
+    #[serde(default)]
+    max_ang_vel: Option<f64>,    // Stop as unstable once any |ω_i| exceeds this (rad/s)
+    #[serde(default)]
+    fixed_mask: Vec<bool>,       // Per link: true pins it at its initial angle; empty = all free
+    #[serde(default)]
+    phase_link: Option<usize>,   // 1-based link whose (θ, ω) series to return as `phase_space`
+    #[serde(default)]
+    measure_period: bool,        // Measure the oscillation period of θ1
+    #[serde(default)]
+    flip_detection: bool,        // n = 2: report when the lower link first flips over the top
+    #[serde(default)]
+    spring: bool,                // Elastic links: `lengths` become rest lengths
+    #[serde(default)]
+    stiffness: String,           // Comma-separated spring constants (N/m), spring mode only
+    #[serde(default)]
+    rod_inertia: bool,           // Links are uniform rods of the given masses, not point bobs
+    #[serde(default = "default_gravity_enabled")]
+    gravity_enabled: bool,       // false switches gravity off entirely (gx/gy are ignored)
+    #[serde(default)]
+    gravity_radius: Option<f64>, // Pivot-to-planet-center distance (m) along (gx, gy); g ∝ 1/r²
+    #[serde(default)]
+    reproducible: bool,          // Omit wall-clock fields so identical requests give identical bytes
+    #[serde(default)]
+    tip_trace: bool,             // Also return the last bob's path as a polyline
+    #[serde(default)]
+    flat: bool,                  // Return `positions` as one flat array (see `Positions::Flat`)
+    #[serde(default)]
+    topology: String,            // Comma-separated parent link per link (0 = pivot); empty = chain
+    #[serde(default)]
+    compute_tensions: bool,      // Also return the axial force in every link per time step
+    #[serde(default)]
+    compute_velocities: bool,    // Also return every bob's Cartesian velocity per time step
+    #[serde(default)]
+    compute_lagrangian: bool,    // Also return L = T − V and H = T + V per time step
+    #[serde(default)]
+    compute_condition_number: bool, // Also return the mass matrix's condition number per time step
+    #[serde(default)]
+    compute_tip_speed: bool,     // Also return the last bob's peak speed and when it occurred
+    #[serde(default)]
+    compute_gravity_work: bool,  // Also return the work gravity did over the run
+    #[serde(default)]
+    quadrature: Quadrature,      // Rule for `gravity_work` and `dissipated_energy` (trapezoidal)
+    #[serde(default)]
+    output_frame: OutputFrame,   // "cartesian" (default) or "polar" bob coordinates
+    #[serde(default)]
+    energy_correction: bool,     // Rescale ω after every step to hold the initial energy
+    #[serde(default)]
+    summary_only: bool,          // Return `summary` scalars instead of per-frame arrays
+    #[serde(default)]
+    final_only: bool,            // Return just the last frame (per-frame arrays of length 1)
+    #[serde(default)]
+    error_estimate: bool,        // Rerun at dt/2 and report a Richardson error estimate
+    #[serde(default)]
+    target_energy: Option<f64>,  // Start from a random state with this total energy (J)
+    #[serde(default)]
+    perturbation_sigma: Option<f64>, // Gaussian noise σ on every initial angle (`angle_unit`)
+    #[serde(default)]
+    normal_mode: Option<usize>,  // Start at rest along this normal mode (0 = lowest frequency)
+    #[serde(default)]
+    mode_amplitude: Option<f64>, // Largest initial angle of that mode's shape (`angle_unit`)
+    #[serde(default)]
+    seed: u64,                   // Seed for `target_energy` sampling and `perturbation_sigma` noise
+    #[serde(default)]
+    damping_model: DampingModel, // "none" (default), "linear", "quadratic" or "coulomb" friction
+    #[serde(default)]
+    damping: String,             // Comma-separated friction coefficients per link (b, c or μ)
+    #[serde(default)]
+    analytic: bool,              // n = 1: evaluate the exact (elliptic) solution, no integration
+    #[serde(default)]
+    angle_unit: AngleUnit,       // "deg" (default) or "rad" for angle inputs and outputs
+    #[serde(default)]
+    warmup_time: f64,            // Unrecorded burn-in (s) before t_max; times then start here
+    #[serde(default)]
+    centripetal_form: CentripetalForm, // Debug: "christoffel" cross-checks C (not in /params)
+}
+
+/// Poincaré section settings: record (θ, ω) of `record_link` whenever
+/// θ of `crossing_link` crosses zero in `direction`. Links are 1-based.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct PoincareParams {
+    #[serde(default = "default_crossing_link")]
+    crossing_link: usize,
+    #[serde(default = "default_record_link")]
+    record_link: usize,
+    #[serde(default)]
+    direction: CrossingDirection,
+}
+
+fn default_crossing_link() -> usize {
+    1
+}
+
+fn default_record_link() -> usize {
+    2
+}
+
+fn default_stride() -> usize {
+    1
+}
+
+fn default_substeps() -> usize {
+    1
+}
+
+fn default_potential_samples() -> usize {
+    181
+}
+
+fn default_flip_dt() -> f64 {
+    0.01
 }
 
+fn default_gy() -> f64 {
+    -9.81
+}
+
+fn default_gravity_enabled() -> bool {
+    true
+}
+
+/// A named starting configuration selectable through `SimParams::preset`.
+struct Preset {
+    name: &'static str,
+    n: usize,
+    masses: &'static str,
+    lengths: &'static str,
+    initial_angles: &'static str, // Degrees
+}
+
+/// Built-in presets. All bobs weigh 1 kg and all links are 1 m long; only the angles differ:
+/// * `single`: one link released from 30° (near-harmonic swing).
+/// * `double`: two links released from 30°, 30° (regular, small-amplitude motion).
+/// * `double_chaos`: two links released from 120°, -10° (the classic chaotic double pendulum).
+/// * `triple`: three links released from 90°, 90°, 90° (horizontal chain, chaotic).
+const PRESETS: [Preset; 4] = [
+    Preset { name: "single", n: 1, masses: "1", lengths: "1", initial_angles: "30" },
+    Preset { name: "double", n: 2, masses: "1,1", lengths: "1,1", initial_angles: "30,30" },
+    Preset {
+        name: "double_chaos",
+        n: 2,
+        masses: "1,1",
+        lengths: "1,1",
+        initial_angles: "120,-10",
+    },
+    Preset {
+        name: "triple",
+        n: 3,
+        masses: "1,1,1",
+        lengths: "1,1,1",
+        initial_angles: "90,90,90",
+    },
+];
+
+impl SimParams {
+    /// Returns a copy with the named preset filling in `n`, `masses`, `lengths` and
+    /// `initial_angles` wherever the request left them unset (0 or empty).
+    fn with_preset(&self, name: &str) -> Result<SimParams, SimError> {
+        let preset = PRESETS.iter().find(|p| p.name == name).ok_or_else(|| {
+            let names: Vec<_> = PRESETS.iter().map(|p| p.name).collect();
+            let reason = format!("unknown preset '{name}' (expected one of {})", names.join(", "));
+            SimError::InvalidParam { field: "preset", reason }
+        })?;
+
+        let fill = |explicit: &str, default: &str| {
+            if explicit.trim().is_empty() { default.to_string() } else { explicit.to_string() }
+        };
+        Ok(SimParams {
+            preset: None,
+            n: if self.n == 0 { preset.n } else { self.n },
+            masses: fill(&self.masses, preset.masses),
+            lengths: fill(&self.lengths, preset.lengths),
+            initial_angles: fill(&self.initial_angles, preset.initial_angles),
+            ..self.clone()
+        })
+    }
+}
+
+/// Number of points covering [0, t_max] in steps of at most `dt`: ceil(|t_max| / dt) + 1.
+/// The solver then steps by t_max / ceil(|t_max| / dt), whose size equals `dt` whenever it
+/// divides `t_max` and is slightly smaller otherwise. `dt` is a magnitude: a negative
+/// `t_max` (backward run) sets the direction.
+fn points_for_dt(t_max: f64, dt: f64) -> Result<usize, SimError> {
+    check_positive("t_max", t_max.abs())?;
+    check_positive("dt", dt)?;
+    let steps = (t_max.abs() / dt).ceil();
+    if steps >= MAX_POINTS as f64 {
+        let reason = format!("t_max / dt + 1 exceeds the maximum of {MAX_POINTS} points");
+        return Err(SimError::InvalidParam { field: "dt", reason });
+    }
+    Ok(steps as usize + 1)
+}
+
+/// Largest number of links accepted by the API (matches the frontend's limit).
+const MAX_N: usize = 150;
+/// Largest number of integration steps accepted per request.
+const MAX_POINTS: usize = 1_000_000;
+
 #[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
 struct SimResponse {
     success: bool,
     animation_data: AnimationData,
     #[serde(skip_serializing_if = "Option::is_none")]
-    message: Option<String>,
+    #[cfg_attr(test, serde(skip_deserializing))]
+    error: Option<SimError>,
 }
 
 #[derive(Serialize, Default)]
+#[cfg_attr(test, derive(Deserialize))]
 struct AnimationData {
-    positions: Vec<Vec<f64>>, // Flattened [x1, y1, x2, y2...] per time step
+    positions: Positions,     // Flattened [x1, y1, x2, y2...] per time step
+    times: Vec<f64>,          // Time of each frame (s)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stride: Option<usize>,      // Values per frame in flat `positions` (2n)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frame_count: Option<usize>, // Frames in flat `positions`
     n: usize,
     limit: f64,               // Boundary for frontend scaling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    solve_millis: Option<f64>, // Wall-clock time of the integration alone (ms), unless `reproducible`
+    final_state: Vec<f64>,    // [θ1..θn, ω1..ωn] at the last frame, usable as `resume_state`
+    center_of_mass: Vec<[f64; 2]>, // Mass-weighted average of bob positions per time step
+    angular_momentum: Vec<f64>,    // Total angular momentum about the pivot per time step
+    velocity_bounds: Vec<[f64; 2]>, // [min, max] of ω_i over the run, per link (phase-plot axes)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    poincare_points: Option<Vec<[f64; 2]>>, // (θ, ω) section points, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phase_space: Option<Vec<[f64; 2]>>,     // (θ, ω) of `phase_link` per time step
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lyapunov: Option<LyapunovEstimate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    divergence: Option<Vec<f64>>, // |y(t) − y_perturbed(t)| per frame (needs `divergence_epsilon`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reversibility_residual: Option<f64>, // |θ(0) - θ after forward + reversed run|
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_collision_time: Option<f64>,   // Time of the first bob contact (needs `min_separation`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blew_up_at_time: Option<f64>,        // Time the run was stopped as unstable (frames end before)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sampled_positions: Option<Vec<Vec<f64>>>, // Positions at `sample_times` (`positions` layout)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    period: Option<f64>, // Measured θ1 period (None if no full cycle fits in t_max)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exact_period: Option<f64>, // n = 1: elliptic-integral period (with `measure_period`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flip_time: Option<f64>,    // First time θ2 passes an odd multiple of π (`flip_detection`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tip_speed: Option<f64>,      // Peak speed of the last bob (m/s, `compute_tip_speed`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tip_speed_time: Option<f64>, // Time of that peak (s)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gravity_work: Option<f64>,       // ∫ gravitational power dt, J (`compute_gravity_work`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dissipated_energy: Option<f64>,  // −∫ friction power dt, J (with a `damping_model`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    length_scale: Option<f64>,       // Factor applied by `normalize_total_length`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tip_path: Option<Vec<[f64; 2]>>,     // Last bob's (x, y) per time step (needs `tip_trace`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tensions: Option<Vec<Vec<f64>>>,     // [T1..Tn] per time step, N (needs `compute_tensions`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lagrangian: Option<Vec<f64>>,        // L = T − V per time step, J (needs `compute_lagrangian`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hamiltonian: Option<Vec<f64>>,       // H = T + V per time step, J (needs `compute_lagrangian`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    condition_number: Option<Vec<f64>>,  // cond₂(M) per time step (`compute_condition_number`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bob_velocities: Option<Vec<Vec<f64>>>, // [ẋ1, ẏ1, ẋ2, ẏ2...] per time step (`positions` layout)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    link_lengths: Option<Vec<Vec<f64>>>, // Spring mode: [r1..rn] per time step
+    #[serde(skip_serializing_if = "Option::is_none")]
+    energy_drift: Option<f64>,           // max |E(t) − E(0)| over the recorded frames (J)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<RunSummary>,         // Scalar outcomes (with `summary_only`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_estimate: Option<f64>,         // Estimated |error| of the final state (Richardson)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    initial_state: Option<Vec<f64>>,     // Generated start [θ1..θn, ω1..ωn], if any
+}
+
+/// Bob coordinates per frame. `Flat` concatenates every frame into one array (frame k
+/// starts at k * stride) so clients can load it straight into a `Float64Array`.
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+#[serde(untagged)]
+enum Positions {
+    Nested(Vec<Vec<f64>>),
+    Flat(Vec<f64>),
+}
+
+impl AnimationData {
+    /// Rewrites the radian-valued outputs in degrees (see `AngleUnit`): (θ, ω) pairs, the ω
+    /// bounds, and the φ of every bob when `polar`. Positions must still be nested.
+    fn angles_to_degrees(&mut self, polar: bool) {
+        let pairs = [&mut self.phase_space, &mut self.poincare_points];
+        let bounds = pairs.into_iter().flatten().chain([&mut self.velocity_bounds]);
+        for pair in bounds.flatten() {
+            *pair = pair.map(f64::to_degrees);
+        }
+        if !polar {
+            return;
+        }
+        let mut frames: Vec<&mut Vec<f64>> = self.sampled_positions.iter_mut().flatten().collect();
+        if let Positions::Nested(nested) = &mut self.positions {
+            frames.extend(nested.iter_mut());
+        }
+        for frame in frames {
+            for bob in frame.chunks_exact_mut(2) {
+                bob[1] = bob[1].to_degrees();
+            }
+        }
+    }
+
+    /// Name of the first field holding a NaN or ±inf. serde_json would silently write those
+    /// as `null`, so `simulate` turns them into a `SimError::NonFinite` instead.
+    fn first_non_finite(&self) -> Option<&'static str> {
+        fn finite<'a>(values: impl IntoIterator<Item = &'a f64>) -> bool {
+            values.into_iter().all(|v| v.is_finite())
+        }
+        let positions = match &self.positions {
+            Positions::Nested(frames) => finite(frames.iter().flatten()),
+            Positions::Flat(values) => finite(values),
+        };
+        let lyapunov = self
+            .lyapunov
+            .as_ref()
+            .is_none_or(|l| finite(&l.running) && l.exponent.is_finite());
+        let summary = self.summary.as_ref().is_none_or(|s| {
+            finite([&s.max_tip_speed, &s.max_height, &s.energy_drift])
+        });
+        let nested = |frames: &Option<Vec<Vec<f64>>>| finite(frames.iter().flatten().flatten());
+        let pairs = |pairs: &Option<Vec<[f64; 2]>>| finite(pairs.iter().flatten().flatten());
+
+        let checks = [
+            ("positions", positions),
+            ("times", finite(&self.times)),
+            ("limit", self.limit.is_finite()),
+            ("solve_millis", finite(&self.solve_millis)),
+            ("final_state", finite(&self.final_state)),
+            ("center_of_mass", finite(self.center_of_mass.iter().flatten())),
+            ("angular_momentum", finite(&self.angular_momentum)),
+            ("velocity_bounds", finite(self.velocity_bounds.iter().flatten())),
+            ("poincare_points", pairs(&self.poincare_points)),
+            ("phase_space", pairs(&self.phase_space)),
+            ("lyapunov", lyapunov),
+            ("divergence", finite(self.divergence.iter().flatten())),
+            ("reversibility_residual", finite(&self.reversibility_residual)),
+            ("first_collision_time", finite(&self.first_collision_time)),
+            ("blew_up_at_time", finite(&self.blew_up_at_time)),
+            ("sampled_positions", nested(&self.sampled_positions)),
+            ("period", finite(&self.period)),
+            ("exact_period", finite(&self.exact_period)),
+            ("flip_time", finite(&self.flip_time)),
+            ("max_tip_speed", finite(&self.max_tip_speed)),
+            ("max_tip_speed_time", finite(&self.max_tip_speed_time)),
+            ("gravity_work", finite(&self.gravity_work)),
+            ("dissipated_energy", finite(&self.dissipated_energy)),
+            ("length_scale", finite(&self.length_scale)),
+            ("tip_path", pairs(&self.tip_path)),
+            ("tensions", nested(&self.tensions)),
+            ("lagrangian", finite(self.lagrangian.iter().flatten())),
+            ("hamiltonian", finite(self.hamiltonian.iter().flatten())),
+            ("condition_number", finite(self.condition_number.iter().flatten())),
+            ("bob_velocities", nested(&self.bob_velocities)),
+            ("link_lengths", nested(&self.link_lengths)),
+            ("energy_drift", finite(&self.energy_drift)),
+            ("summary", summary),
+            ("error_estimate", finite(&self.error_estimate)),
+            ("initial_state", finite(self.initial_state.iter().flatten())),
+        ];
+        checks.iter().find(|(_, ok)| !ok).map(|(field, _)| *field)
+    }
+}
+
+impl Default for Positions {
+    fn default() -> Self {
+        Positions::Nested(Vec::new())
+    }
+}
+
+/// Coordinates of each bob in `positions` and `sampled_positions`: `Cartesian` pairs are
+/// (x, y); `Polar` pairs are (r, φ) about the origin (the pivot unless `pivot_x`/`pivot_y`
+/// move it), φ = atan2(y, x) in radians.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+enum OutputFrame {
+    #[default]
+    Cartesian,
+    Polar,
+}
+
+/// Unit of the angles in a `/simulate` request and response, degrees by default. Inputs
+/// (`initial_angles`, `perturbation_sigma`, `mode_amplitude`) and angle outputs (`phase_space`,
+/// `poincare_points`, `velocity_bounds`, polar φ) both use it, with ω in unit/s. Full state
+/// vectors (`resume_state`, `final_state`, `initial_state`) are always radians so a run can be
+/// resumed verbatim.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+enum AngleUnit {
+    #[default]
+    Deg,
+    Rad,
+}
+
+impl AngleUnit {
+    /// Converts an angle (or angular rate) given in this unit to radians.
+    fn to_radians(self, value: f64) -> f64 {
+        match self {
+            AngleUnit::Deg => value.to_radians(),
+            AngleUnit::Rad => value,
+        }
+    }
+}
+
+/// Scalar outcomes of a run for parameter sweeps, accumulated over every integration step
+/// (not just the recorded frames) so no trajectory has to be kept.
+#[derive(Serialize, Default)]
+#[cfg_attr(test, derive(Deserialize))]
+struct RunSummary {
+    max_tip_speed: f64,  // Largest speed of the last bob (m/s)
+    max_height: f64,     // Highest y any bob reached, relative to the pivot (m)
+    went_over_top: bool, // Some bob rose above the pivot (max_height > 0), for any link
+    energy_drift: f64,   // max |E(t) − E(0)| (J)
+    #[serde(skip)]
+    initial_energy: Option<f64>,
+}
+
+impl RunSummary {
+    /// Folds the state of one integration step into the running statistics.
+    fn observe(&mut self, solver: &NPendulumSolver, y: &DVector<f64>) {
+        let n = solver.n;
+        let energy = solver.total_energy(y);
+        let e0 = match self.initial_energy {
+            Some(e0) => e0,
+            None => {
+                // First step: nothing reached yet
+                self.max_height = f64::NEG_INFINITY;
+                *self.initial_energy.insert(energy)
+            }
+        };
+        let bobs = solver.bob_positions(y);
+        let (vx, vy) = solver.bob_velocities(y)[n - 1];
+
+        self.max_tip_speed = self.max_tip_speed.max(vx.hypot(vy));
+        self.max_height = bobs.iter().fold(self.max_height, |h, &(_, y)| h.max(y));
+        self.went_over_top = self.max_height > 0.0;
+        self.energy_drift = self.energy_drift.max((energy - e0).abs());
+    }
+}
+
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+struct LyapunovEstimate {
+    running: Vec<f64>, // Running estimate per frame (1/s)
+    exponent: f64,     // Final estimate at t_max
 }
 
 /// Helper: Parses a comma-separated string into a Vec<f64>.
-fn parse_csv_f64(s: &str) -> Vec<f64> {
+/// Empty tokens (e.g. a trailing comma) are skipped; anything else must be a finite number
+/// (`NaN` and `inf` parse as floats but are rejected).
+fn parse_csv_f64(field: &'static str, s: &str) -> Result<Vec<f64>, SimError> {
+    // Every CSV field holds at most one value per link: refuse huge inputs before allocating
+    let tokens = s.split(',').filter(|x| !x.trim().is_empty()).count();
+    if tokens > MAX_N {
+        return Err(SimError::TooLarge { field, max: MAX_N, got: tokens });
+    }
     s.split(',')
-        .filter_map(|x| x.trim().parse::<f64>().ok())
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            let value = x.parse::<f64>().map_err(|_| SimError::ParseError {
+                field,
+                token: x.to_string(),
+            })?;
+            if !value.is_finite() {
+                let reason = format!("'{x}' is not a finite number");
+                return Err(SimError::InvalidParam { field, reason });
+            }
+            Ok(value)
+        })
         .collect()
 }
 
+/// Helper: Parses `initial_angles` into radians: either CSV in `unit`, or a keyword putting
+/// all `n` links in a straight line: `horizontal` (90°), `vertical_down` (0°) or
+/// `vertical_up` (180°).
+fn parse_initial_angles(s: &str, n: usize, unit: AngleUnit) -> Result<Vec<f64>, SimError> {
+    let straight: f64 = match s.trim() {
+        "horizontal" => 90.0,
+        "vertical_down" => 0.0,
+        "vertical_up" => 180.0,
+        _ => {
+            let values = parse_csv_f64("initial_angles", s)?;
+            return Ok(values.into_iter().map(|v| unit.to_radians(v)).collect());
+        }
+    };
+    Ok(vec![straight.to_radians(); n])
+}
+
+/// Helper: Ensures a parsed field has exactly `expected` values.
+fn check_len<V>(field: &'static str, values: &[V], expected: usize) -> Result<(), SimError> {
+    if values.len() != expected {
+        return Err(SimError::LengthMismatch { field, expected, got: values.len() });
+    }
+    Ok(())
+}
+
+/// Helper: Ensures every value of a numeric array parameter is finite (query strings and
+/// MessagePack can carry NaN and ±inf even though JSON cannot).
+fn check_all_finite(field: &'static str, values: &[f64]) -> Result<(), SimError> {
+    match values.iter().position(|v| !v.is_finite()) {
+        Some(i) => Err(SimError::InvalidParam {
+            field,
+            reason: format!("entry {} is {}, must be a finite number", i + 1, values[i]),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Helper: Ensures a numeric parameter is finite and strictly positive.
+fn check_positive(field: &'static str, value: f64) -> Result<(), SimError> {
+    if !(value.is_finite() && value > 0.0) {
+        let reason = "must be a positive number".to_string();
+        return Err(SimError::InvalidParam { field, reason });
+    }
+    Ok(())
+}
+
+/// Helper: Ensures every per-link value is strictly positive, naming the first offending link.
+fn check_all_positive(field: &'static str, values: &[f64]) -> Result<(), SimError> {
+    match values.iter().position(|&v| v <= 0.0) {
+        Some(i) => Err(SimError::InvalidParam {
+            field,
+            reason: format!("link {} has value {}, must be positive", i + 1, values[i]),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Helper: Ensures a 1-based link index refers to an existing link.
+fn check_link(field: &'static str, link: usize, n: usize) -> Result<(), SimError> {
+    if link == 0 || link > n {
+        return Err(SimError::InvalidParam { field, reason: format!("must be in 1..={n}") });
+    }
+    Ok(())
+}
+
+/// Helper: The faster of the peak so far and a tip `speed` at time `t`, as (speed, time).
+/// Ties keep the earlier peak.
+fn faster_tip(best: Option<(f64, f64)>, speed: f64, t: f64) -> Option<(f64, f64)> {
+    match best {
+        Some((max, _)) if max >= speed => best,
+        _ => Some((speed, t)),
+    }
+}
+
 /// Helper: Converts angular states (theta) into Cartesian coordinates (x, y).
 /// Returns a vector of time steps, where each step is [x1, y1, x2, y2, ...].
-fn compute_positions(sol: &[DVector<f64>], n: usize, lengths: &[f64]) -> Vec<Vec<f64>> {
+/// Each bob hangs from its parent's bob (`parents` is 1-based, 0 = pivot).
+/// Mismatched lengths or a parent that is not an earlier bob give an error, not a panic.
+fn compute_positions(
+    sol: &[DVector<f64>],
+    n: usize,
+    lengths: &[f64],
+    parents: &[usize],
+) -> Result<Vec<Vec<f64>>, SimError> {
+    for (field, got) in [("lengths", lengths.len()), ("parents", parents.len())] {
+        if got != n + 1 {
+            return Err(SimError::LengthMismatch { field, expected: n, got: got.saturating_sub(1) });
+        }
+    }
+    if let Some(k) = (1..=n).find(|&k| parents[k] >= k) {
+        let reason = format!("bob {k} must hang from the pivot or an earlier bob");
+        return Err(SimError::InvalidParam { field: "parents", reason });
+    }
     let mut positions = Vec::with_capacity(sol.len());
 
     for state in sol {
-        let mut step_coords = Vec::with_capacity(2 * n);
-        let mut curr_x = 0.0;
-        let mut curr_y = 0.0;
+        if state.len() < n {
+            let got = state.len();
+            return Err(SimError::LengthMismatch { field: "state", expected: n, got });
+        }
+        let mut step_coords: Vec<f64> = Vec::with_capacity(2 * n);
 
         // state contains [theta_1 ... theta_n, omega_1 ... omega_n]
         // logic.rs uses 1-based indexing for lengths (index 0 is dummy)
@@ -52,6 +685,10 @@ fn compute_positions(sol: &[DVector<f64>], n: usize, lengths: &[f64]) -> Vec<Vec
         for k in 0..n {
             let theta = state[k]; // theta_(k+1)
             let len = lengths[k + 1]; // L_(k+1)
+            let (mut curr_x, mut curr_y) = match parents[k + 1] {
+                0 => (0.0, 0.0),
+                p => (step_coords[2 * (p - 1)], step_coords[2 * (p - 1) + 1]),
+            };
 
             curr_x += len * theta.sin();
             curr_y -= len * theta.cos();
@@ -61,68 +698,2884 @@ fn compute_positions(sol: &[DVector<f64>], n: usize, lengths: &[f64]) -> Vec<Vec
         }
         positions.push(step_coords);
     }
+    Ok(positions)
+}
+
+/// Helper: Parses `topology` into [0, p1, ..., pn], where p_i is the link that link i hangs
+/// from (0 = pivot) and must precede it. Empty means the serial chain (p_i = i − 1), which
+/// is returned as `None`.
+fn parse_topology(s: &str, n: usize) -> Result<Option<Vec<usize>>, SimError> {
+    let values = parse_csv_f64("topology", s)?;
+    if values.is_empty() {
+        return Ok(None);
+    }
+    check_len("topology", &values, n)?;
+
+    let mut parents = vec![0];
+    for (i, &p) in values.iter().enumerate() {
+        if p.fract() != 0.0 || p < 0.0 || p > i as f64 {
+            return Err(SimError::InvalidParam {
+                field: "topology",
+                reason: format!("link {} must hang from the pivot (0) or a link in 1..={i}", i + 1),
+            });
+        }
+        parents.push(p as usize);
+    }
+    Ok(Some(parents))
+}
+
+/// Helper: Pairs `damping_model` with its per-link coefficients, padded to [0, c1, ..., cn].
+/// `none` takes no coefficients; the others need one finite, non-negative value per link.
+fn parse_damping(model: DampingModel, s: &str, n: usize) -> Result<Option<Damping<f64>>, SimError> {
+    let values = parse_csv_f64("damping", s)?;
+    if model == DampingModel::None {
+        if !values.is_empty() {
+            let reason = "requires a damping_model".to_string();
+            return Err(SimError::InvalidParam { field: "damping", reason });
+        }
+        return Ok(None);
+    }
+    check_len("damping", &values, n)?;
+    check_all_finite("damping", &values)?;
+    if let Some(i) = values.iter().position(|&c| c < 0.0) {
+        return Err(SimError::InvalidParam {
+            field: "damping",
+            reason: format!("link {} has value {}, must not be negative", i + 1, values[i]),
+        });
+    }
+    let mut coefficients = vec![0.0];
+    coefficients.extend(values);
+    Ok(Some(Damping { model, coefficients }))
+}
+
+/// Helper: Shifts every (x, y) pair of `positions` by `pivot`, moving the chain from the
+/// origin to the requested anchor point.
+fn translate_positions(positions: &mut [Vec<f64>], pivot: [f64; 2]) {
+    if pivot == [0.0, 0.0] {
+        return;
+    }
+    for step_coords in positions {
+        for bob in step_coords.chunks_exact_mut(2) {
+            bob[0] += pivot[0];
+            bob[1] += pivot[1];
+        }
+    }
+}
+
+/// Helper: Rewrites every (x, y) pair of `positions` in place as (hypot(x, y), atan2(y, x)).
+fn to_polar(positions: &mut [Vec<f64>]) {
+    for step_coords in positions {
+        for bob in step_coords.chunks_exact_mut(2) {
+            let (x, y) = (bob[0], bob[1]);
+            bob[0] = x.hypot(y);
+            bob[1] = y.atan2(x);
+        }
+    }
+}
+
+/// Helper: Extracts the last bob's (x, y) from each time step of `positions`.
+fn compute_tip_path(positions: &[Vec<f64>]) -> Vec<[f64; 2]> {
+    positions
+        .iter()
+        .filter_map(|step_coords| match step_coords.as_slice() {
+            [.., x, y] => Some([*x, *y]),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Helper: Range [min, max] of each link's angular velocity over all recorded states,
+/// reading the n entries starting at `offset` (ω1..ωn) of every state vector.
+fn compute_velocity_bounds(sol: &[DVector<f64>], n: usize, offset: usize) -> Vec<[f64; 2]> {
+    (0..n)
+        .map(|k| {
+            sol.iter()
+                .map(|y| y[offset + k])
+                .fold([f64::INFINITY, f64::NEG_INFINITY], |[lo, hi], w| [lo.min(w), hi.max(w)])
+        })
+        .collect()
+}
+
+/// Helper: (θ, ω) of 1-based `link` in every recorded state, reading ω from the n entries
+/// starting at `offset` like `compute_velocity_bounds`.
+fn compute_phase_space(sol: &[DVector<f64>], link: usize, offset: usize) -> Vec<[f64; 2]> {
+    sol.iter().map(|y| [y[link - 1], y[offset + link - 1]]).collect()
+}
+
+/// Helper: Replaces each bob coordinate with the midpoint of the link ending at it,
+/// i.e. the centers of mass of uniform rods. Root links start at `pivot`.
+fn rod_midpoints(positions: &[Vec<f64>], parents: &[usize], pivot: [f64; 2]) -> Vec<Vec<f64>> {
+    positions
+        .iter()
+        .map(|step_coords| {
+            let mut mids = Vec::with_capacity(step_coords.len());
+            for (k, bob) in step_coords.chunks_exact(2).enumerate() {
+                let prev = match parents[k + 1] {
+                    0 => pivot,
+                    p => [step_coords[2 * (p - 1)], step_coords[2 * (p - 1) + 1]],
+                };
+                mids.push(0.5 * (prev[0] + bob[0]));
+                mids.push(0.5 * (prev[1] + bob[1]));
+            }
+            mids
+        })
+        .collect()
+}
+
+/// Helper: Computes the chain's center of mass for each time step.
+/// Bobs are point masses, so this is the mass-weighted average of the bob coordinates.
+fn compute_center_of_mass(positions: &[Vec<f64>], masses: &[f64]) -> Vec<[f64; 2]> {
+    let total_mass: f64 = masses.iter().sum();
+
     positions
+        .iter()
+        .map(|step_coords| {
+            let (mut cx, mut cy) = (0.0, 0.0);
+            for (bob, m) in step_coords.chunks_exact(2).zip(masses) {
+                cx += m * bob[0];
+                cy += m * bob[1];
+            }
+            [cx / total_mass, cy / total_mass]
+        })
+        .collect()
+}
+
+/// MIME type clients send in `Accept` to receive MessagePack instead of JSON.
+#[cfg(feature = "server")]
+const MSGPACK_MIME: &str = "application/msgpack";
+
+/// Helper: Content type the client's `Accept` header asks for: MessagePack if listed,
+/// JSON otherwise.
+#[cfg(feature = "server")]
+fn response_format(req: &HttpRequest) -> &'static str {
+    let wants_msgpack = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| {
+            accept.split(',').any(|m| m.split(';').next().unwrap_or("").trim() == MSGPACK_MIME)
+        });
+    if wants_msgpack { MSGPACK_MIME } else { "application/json" }
+}
+
+/// Helper: Serializes `body` in `content_type` (see `response_format`). MessagePack maps keep
+/// the field names, so both carry the same structure.
+#[cfg(feature = "server")]
+fn encode<T: Serialize>(body: &T, content_type: &'static str) -> Result<CachedBody> {
+    let bytes = if content_type == MSGPACK_MIME {
+        rmp_serde::to_vec_named(body).map_err(ErrorInternalServerError)?
+    } else {
+        serde_json::to_vec(body).map_err(ErrorInternalServerError)?
+    };
+    Ok(CachedBody { content_type, bytes: bytes.into() })
+}
+
+/// `JsonConfig` error handler: oversized bodies (413) and non-JSON content types (415) get
+/// the usual `{ success: false, error: { code, detail } }` envelope instead of a bare status.
+#[cfg(feature = "server")]
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let (status, error) = match &err {
+        JsonPayloadError::Overflow { limit }
+        | JsonPayloadError::OverflowKnownLength { limit, .. } => {
+            (StatusCode::PAYLOAD_TOO_LARGE, SimError::BodyTooLarge { limit: *limit })
+        }
+        JsonPayloadError::ContentType => (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            SimError::InvalidParam {
+                field: "content-type",
+                reason: "must be application/json".to_string(),
+            },
+        ),
+        _ => return err.into(),
+    };
+    let body = serde_json::json!({ "success": false, "error": error });
+    InternalError::from_response(err, HttpResponse::build(status).json(body)).into()
+}
+
+/// Wall-clock limit for a single simulation (app data, see `main`).
+#[cfg(feature = "server")]
+#[derive(Clone, Copy)]
+pub struct SolveTimeout(pub Duration);
+
+#[cfg(feature = "server")]
+impl SolveTimeout {
+    /// Deadline for a solve starting now.
+    fn deadline(&self) -> Deadline {
+        Deadline { at: Instant::now() + self.0, limit_secs: self.0.as_secs_f64() }
+    }
+}
+
+/// Sequential id carried by each `simulate` span, to correlate a request's log lines.
+#[cfg(feature = "server")]
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Runs the simulation on the blocking pool so it never stalls the worker. The integration
+/// loop aborts itself at the deadline; the async timeout answers the client even if some
+/// post-processing outlasts it. Each call runs in a `simulate` span that records the request
+/// size and, once done, the outcome, which is also counted in `metrics`.
+#[cfg(feature = "server")]
+async fn simulate_with_timeout(
+    params: SimParams,
+    timeout: SolveTimeout,
+    metrics: &Metrics,
+) -> Result<SimResponse> {
+    let span = tracing::info_span!(
+        "simulate",
+        request_id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed),
+        preset = params.preset.as_deref(),
+        n = params.n,
+        n_points = params.n_points,
+        dt = params.dt,
+        solve_millis = tracing::field::Empty,
+        success = tracing::field::Empty,
+    );
+    async move {
+        let requested_n = params.n;
+        let started = Instant::now();
+        let deadline = timeout.deadline();
+        let run = web::block(move || simulate(&params, &mut |_| {}, Some(deadline)));
+        let response = match rt::time::timeout(timeout.0, run).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(err)) => {
+                tracing::error!(error = %err, "simulation worker failed");
+                metrics.observe_simulation(requested_n, started.elapsed(), false);
+                return Err(err.into());
+            }
+            Err(_) => SimResponse {
+                success: false,
+                animation_data: AnimationData::default(),
+                error: Some(SimError::TimedOut { limit_secs: deadline.limit_secs }),
+            },
+        };
+
+        // Presets fill in n, so prefer the size the solve actually used
+        let n = if response.animation_data.n > 0 { response.animation_data.n } else { requested_n };
+        metrics.observe_simulation(n, started.elapsed(), response.success);
+
+        let span = tracing::Span::current();
+        span.record("success", response.success);
+        // Presets fill in n after the span was opened
+        if response.animation_data.n > 0 {
+            span.record("n", response.animation_data.n);
+        }
+        if let Some(millis) = response.animation_data.solve_millis {
+            span.record("solve_millis", millis);
+        }
+        match &response.error {
+            None => tracing::info!("simulation finished"),
+            Some(err) => tracing::warn!(code = err.code(), detail = %err, "simulation failed"),
+        }
+        Ok(response)
+    }
+    .instrument(span)
+    .await
+}
+
+/// Header telling clients (and logs) whether `/simulate` was answered from the response cache.
+#[cfg(feature = "server")]
+const CACHE_HEADER: &str = "x-cache";
+
+/// Answers from `cache` when an identical request (after filling in its preset) was solved
+/// before, and otherwise solves and caches successful responses. Only `reproducible` requests
+/// are cached, since a replayed `solve_millis` would pass off an old timing as fresh. Failures
+/// are not cached: timeouts depend on load and validation errors are cheap to recompute.
+#[cfg(feature = "server")]
+async fn simulate_cached(
+    req: &HttpRequest,
+    params: SimParams,
+    timeout: SolveTimeout,
+    metrics: &Metrics,
+    cache: &ResponseCache,
+) -> Result<HttpResponse> {
+    let content_type = response_format(req);
+    let key = if params.reproducible {
+        let normalized = match params.preset.as_deref() {
+            Some(name) => params.with_preset(name).unwrap_or_else(|_| params.clone()),
+            None => params.clone(),
+        };
+        serde_json::to_vec(&normalized).ok().map(|bytes| ResponseCache::key(&bytes, content_type))
+    } else {
+        None
+    };
+
+    if let Some(hit) = key.and_then(|key| cache.get(key)) {
+        tracing::info!(preset = params.preset.as_deref(), "simulation served from cache");
+        return Ok(HttpResponse::Ok()
+            .content_type(hit.content_type)
+            .insert_header((CACHE_HEADER, "HIT"))
+            .body(hit.bytes));
+    }
+
+    let response = simulate_with_timeout(params, timeout, metrics).await?;
+    let encoded = encode(&response, content_type)?;
+    if let (Some(key), true) = (key, response.success) {
+        cache.insert(key, encoded.clone());
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(encoded.content_type)
+        .insert_header((CACHE_HEADER, "MISS"))
+        .body(encoded.bytes))
 }
 
 /// Main Handler: Orchestrates parsing, solving, and response formatting.
-pub async fn simulate_handler(params: web::Json<SimParams>) -> Result<HttpResponse> {
-    // 1. Parse Inputs
-    let masses = parse_csv_f64(&params.masses);
-    let lengths = parse_csv_f64(&params.lengths);
-    let angles_deg = parse_csv_f64(&params.initial_angles);
+/// Responds with MessagePack when requested via `Accept: application/msgpack`.
+#[cfg(feature = "server")]
+pub async fn simulate_handler(
+    req: HttpRequest,
+    params: web::Json<SimParams>,
+    timeout: web::Data<SolveTimeout>,
+    metrics: web::Data<Metrics>,
+    cache: web::Data<ResponseCache>,
+) -> Result<HttpResponse> {
+    simulate_cached(&req, params.into_inner(), **timeout, &metrics, &cache).await
+}
 
-    // 2. Validate Inputs
-    if masses.len() != params.n || lengths.len() != params.n || angles_deg.len() != params.n {
-        return Ok(HttpResponse::Ok().json(SimResponse {
+/// GET variant of `simulate_handler` reading the same parameters from the query string,
+/// e.g. `/simulate?n=2&masses=1,1&lengths=1,1&initial_angles=90,45&t_max=10&n_points=2000`
+/// or `/simulate?preset=double_chaos&t_max=10&n_points=2000`.
+/// Nested options (`poincare_section`, `resume_state`) are only available via POST.
+#[cfg(feature = "server")]
+pub async fn simulate_get_handler(
+    req: HttpRequest,
+    params: web::Query<SimParams>,
+    timeout: web::Data<SolveTimeout>,
+    metrics: web::Data<Metrics>,
+    cache: web::Data<ResponseCache>,
+) -> Result<HttpResponse> {
+    simulate_cached(&req, params.into_inner(), **timeout, &metrics, &cache).await
+}
+
+/// Minimum progress (fraction of the run) between two SSE progress events.
+#[cfg(feature = "server")]
+const SSE_PROGRESS_STEP: f64 = 0.02;
+
+/// Helper: Formats one Server-Sent Event with a JSON payload.
+#[cfg(feature = "server")]
+fn sse_event<T: Serialize>(event: Option<&str>, data: &T) -> web::Bytes {
+    let data = serde_json::to_string(data).unwrap_or_default();
+    let frame = match event {
+        Some(name) => format!("event: {name}\ndata: {data}\n\n"),
+        None => format!("data: {data}\n\n"),
+    };
+    web::Bytes::from(frame)
+}
+
+/// Runs the simulation on the blocking pool and streams it as Server-Sent Events:
+/// `data: {"progress": p}` roughly every 2% of the integration (p in (0, 1]), then a final
+/// `event: result` whose data is the usual `SimResponse` envelope (including errors).
+#[cfg(feature = "server")]
+fn simulate_sse(params: SimParams, timeout: SolveTimeout) -> HttpResponse {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let deadline = timeout.deadline();
+
+    rt::task::spawn_blocking(move || {
+        let mut last = 0.0;
+        let mut report = |progress: f64| {
+            if progress - last >= SSE_PROGRESS_STEP || progress >= 1.0 {
+                last = progress;
+                // A closed channel means the client went away; finish quietly
+                let _ = tx.send(sse_event(None, &serde_json::json!({ "progress": progress })));
+            }
+        };
+        let response = simulate(&params, &mut report, Some(deadline));
+        let _ = tx.send(sse_event(Some("result"), &response));
+    });
+
+    let events = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|frame| (Ok::<_, actix_web::Error>(frame), rx))
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        // Keep the compression middleware from buffering the event stream
+        .insert_header(ContentEncoding::Identity)
+        .streaming(events)
+}
+
+/// SSE variant of `simulate_handler` (see `simulate_sse`) taking a JSON body.
+#[cfg(feature = "server")]
+pub async fn simulate_sse_handler(
+    params: web::Json<SimParams>,
+    timeout: web::Data<SolveTimeout>,
+) -> HttpResponse {
+    simulate_sse(params.into_inner(), **timeout)
+}
+
+/// SSE variant reading the query string like `simulate_get_handler`, so browsers can
+/// subscribe with a plain `EventSource`.
+#[cfg(feature = "server")]
+pub async fn simulate_sse_get_handler(
+    params: web::Query<SimParams>,
+    timeout: web::Data<SolveTimeout>,
+) -> HttpResponse {
+    simulate_sse(params.into_inner(), **timeout)
+}
+
+/// Runs the simulation and wraps the outcome in the response envelope.
+/// Only `reproducible` responses are byte-identical for identical requests: the default ones
+/// carry the wall-clock `solve_millis`. Everything else is a pure function of the parameters,
+/// as the integration is sequential and the parallel matrix assembly writes each entry from a
+/// fixed serial sum (see `math::PARALLEL_THRESHOLD`).
+fn simulate(
+    params: &SimParams,
+    on_progress: &mut dyn FnMut(f64),
+    deadline: Option<Deadline>,
+) -> SimResponse {
+    let result = run_simulation(params, on_progress, deadline).and_then(|data| {
+        match data.first_non_finite() {
+            Some(field) => Err(SimError::NonFinite { field }),
+            None => Ok(data),
+        }
+    });
+    let result = result.map(|mut data| {
+        if params.reproducible {
+            data.solve_millis = None;
+        }
+        // Everything derived from positions (centre of mass, tip path, ...) stays Cartesian
+        if params.output_frame == OutputFrame::Polar {
+            if let Positions::Nested(frames) = &mut data.positions {
+                to_polar(frames);
+            }
+            if let Some(sampled) = &mut data.sampled_positions {
+                to_polar(sampled);
+            }
+        }
+        if params.angle_unit == AngleUnit::Deg {
+            data.angles_to_degrees(params.output_frame == OutputFrame::Polar);
+        }
+        if params.flat {
+            if let Positions::Nested(frames) = std::mem::take(&mut data.positions) {
+                data.stride = Some(2 * data.n);
+                data.frame_count = Some(frames.len());
+                data.positions = Positions::Flat(frames.concat());
+            }
+        }
+        data
+    });
+    match result {
+        // Keep the frames before the blow-up, but flag the run as failed
+        Ok(animation_data) if animation_data.blew_up_at_time.is_some() => SimResponse {
+            success: false,
+            error: animation_data.blew_up_at_time.map(|time| SimError::BlewUp { time }),
+            animation_data,
+        },
+        Ok(animation_data) => SimResponse {
+            success: true,
+            animation_data,
+            error: None,
+        },
+        Err(err) => SimResponse {
             success: false,
             animation_data: AnimationData::default(),
-            message: Some(format!(
-                "Input length mismatch. Expected {}, got M:{}, L:{}, A:{}",
-                params.n, masses.len(), lengths.len(), angles_deg.len()
-            )),
-        }));
+            error: Some(err),
+        },
     }
+}
 
-    // 3. Prepare Physics Vectors (1-based indexing padding)
-    // We prepend 0.0 because the physics logic (math.rs) expects 1-based indices [dummy, m1, m2...]
-    let mut full_masses = vec![0.0];
-    full_masses.extend(&masses);
+/// Runs a `/simulate` request given as JSON, with no deadline, and returns the response body
+/// the server would send. A body that does not deserialize gets the usual error envelope.
+pub fn simulate_json(params_json: &str) -> String {
+    let response = match serde_json::from_str::<SimParams>(params_json) {
+        Ok(params) => simulate(&params, &mut |_| {}, None),
+        Err(err) => SimResponse {
+            success: false,
+            animation_data: AnimationData::default(),
+            error: Some(SimError::InvalidParam { field: "params_json", reason: err.to_string() }),
+        },
+    };
+    serde_json::to_string(&response).unwrap_or_else(|err| {
+        let error = SimError::InvalidParam { field: "response", reason: err.to_string() };
+        serde_json::json!({ "success": false, "error": error }).to_string()
+    })
+}
 
-    let mut full_lengths = vec![0.0];
-    full_lengths.extend(&lengths);
+/// Validates the parameters, runs the solver and converts the result for the frontend.
+/// `on_progress` receives the fraction of integration steps completed (see `logic`).
+fn run_simulation(
+    params: &SimParams,
+    on_progress: &mut dyn FnMut(f64),
+    deadline: Option<Deadline>,
+) -> Result<AnimationData, SimError> {
+    // 0. Fill in the preset, if any (explicit fields take precedence), and the step count
+    let mut resolved = match &params.preset {
+        Some(name) => params.with_preset(name)?,
+        None => params.clone(),
+    };
+    if let Some(dt) = params.dt {
+        resolved.n_points = points_for_dt(params.t_max, dt)?;
+    }
+    let params = &resolved;
 
-    let mut full_angles = vec![0.0];
-    full_angles.extend(angles_deg.iter().map(|d| d.to_radians()));
+    // 1. Parse Inputs (n first: the angle keywords expand to n values)
+    if params.n > MAX_N {
+        return Err(SimError::TooLarge { field: "n", max: MAX_N, got: params.n });
+    }
+    if params.n == 0 {
+        return Err(SimError::InvalidParam { field: "n", reason: "must be at least 1".to_string() });
+    }
+    let masses = parse_csv_f64("masses", &params.masses)?;
+    let mut lengths = parse_csv_f64("lengths", &params.lengths)?;
+    let angle_unit = params.angle_unit;
+    let angles = parse_initial_angles(&params.initial_angles, params.n, angle_unit)?;
 
-    let initial_ang_vels = vec![0.0; params.n + 1]; // Start from rest
+    // 2. Validate Inputs
+    if params.n_points > MAX_POINTS {
+        return Err(SimError::TooLarge { field: "n_points", max: MAX_POINTS, got: params.n_points });
+    }
+    if params.n_points < 2 {
+        return Err(SimError::InvalidParam {
+            field: "n_points",
+            reason: "must be at least 2".to_string(),
+        });
+    }
+    if !params.t_max.is_finite() {
+        let reason = "must be a finite number".to_string();
+        return Err(SimError::InvalidParam { field: "t_max", reason });
+    }
+    // Both estimate rates per unit of elapsed time, assumed positive
+    if params.t_max < 0.0 {
+        let forward_only = [
+            ("lyapunov_epsilon", params.lyapunov_epsilon.is_some()),
+            ("measure_period", params.measure_period),
+            ("warmup_time", params.warmup_time > 0.0),
+        ];
+        if let Some((field, _)) = forward_only.iter().find(|(_, set)| *set) {
+            let reason = "requires a forward run (t_max > 0)".to_string();
+            return Err(SimError::InvalidParam { field, reason });
+        }
+    }
+    if params.substeps == 0 {
+        return Err(SimError::InvalidParam {
+            field: "substeps",
+            reason: "must be at least 1".to_string(),
+        });
+    }
+    // Bound the total number of RK4 steps, not just the recorded ones
+    let max_substeps = MAX_POINTS / params.n_points;
+    if params.substeps > max_substeps {
+        return Err(SimError::TooLarge { field: "substeps", max: max_substeps, got: params.substeps });
+    }
+    if params.output_stride == 0 {
+        return Err(SimError::InvalidParam {
+            field: "output_stride",
+            reason: "must be at least 1".to_string(),
+        });
+    }
+    if !(params.warmup_time.is_finite() && params.warmup_time >= 0.0) {
+        let reason = "must be a finite, non-negative number".to_string();
+        return Err(SimError::InvalidParam { field: "warmup_time", reason });
+    }
+    // Both start their own pass from the unwarmed state at t = 0
+    if params.warmup_time > 0.0 {
+        let from_start = [("analytic", params.analytic), ("flip_detection", params.flip_detection)];
+        if let Some((field, _)) = from_start.iter().find(|(_, set)| *set) {
+            let reason = "cannot be combined with warmup_time".to_string();
+            return Err(SimError::InvalidParam { field, reason });
+        }
+    }
 
-    // 4. Initialize Solver
-    let solver = NPendulumSolver::new(params.n, full_masses, full_lengths.clone());
+    // Summary runs keep only the first and last frames, so nothing may need the others
+    if params.summary_only {
+        let per_frame = [
+            ("poincare_section", params.poincare_section.is_some()),
+            ("lyapunov_epsilon", params.lyapunov_epsilon.is_some()),
+            ("divergence_epsilon", params.divergence_epsilon.is_some()),
+            ("reversibility_check", params.reversibility_check),
+            ("sample_times", params.sample_times.is_some()),
+            ("measure_period", params.measure_period),
+            ("tip_trace", params.tip_trace),
+            ("compute_tensions", params.compute_tensions),
+            ("compute_velocities", params.compute_velocities),
+            ("compute_lagrangian", params.compute_lagrangian),
+            ("compute_condition_number", params.compute_condition_number),
+            ("phase_link", params.phase_link.is_some()),
+        ];
+        if let Some((field, _)) = per_frame.iter().find(|(_, set)| *set) {
+            let reason = "is not supported with summary_only".to_string();
+            return Err(SimError::InvalidParam { field, reason });
+        }
+    }
 
-    // 5. Run Simulation
-    // returns (time_vector, state_vectors)
-    let (_t, sol) = solver.solve(
-        full_angles,
-        initial_ang_vels,
-        params.t_max,
-        params.n_points,
-    );
+    // Final-only runs keep the last frame alone, so nothing may need the path leading there
+    if params.final_only {
+        let path = [
+            ("summary_only", params.summary_only),
+            ("poincare_section", params.poincare_section.is_some()),
+            ("lyapunov_epsilon", params.lyapunov_epsilon.is_some()),
+            ("divergence_epsilon", params.divergence_epsilon.is_some()),
+            ("reversibility_check", params.reversibility_check),
+            ("sample_times", params.sample_times.is_some()),
+            ("measure_period", params.measure_period),
+            ("tip_trace", params.tip_trace),
+        ];
+        if let Some((field, _)) = path.iter().find(|(_, set)| *set) {
+            let reason = "is not supported with final_only".to_string();
+            return Err(SimError::InvalidParam { field, reason });
+        }
+    }
 
-    // 6. Post-Process Results
-    // Calculate display limit (Total length + padding)
-    let limit: f64 = lengths.iter().sum::<f64>() + 0.5;
-    
-    // Convert angles to Cartesian coordinates for the frontend
-    let positions = compute_positions(&sol, params.n, &full_lengths);
+    // fps frames are interpolated after the run, so nothing may rely on the solver's frames
+    if let Some(fps) = params.fps {
+        check_positive("fps", fps)?;
+        if (params.t_max.abs() * fps).floor() >= MAX_POINTS as f64 {
+            let reason = format!("t_max · fps + 1 exceeds the maximum of {MAX_POINTS} frames");
+            return Err(SimError::InvalidParam { field: "fps", reason });
+        }
+        let solver_frames = [
+            ("summary_only", params.summary_only),
+            ("final_only", params.final_only),
+            ("output_stride", params.output_stride != 1),
+            ("lyapunov_epsilon", params.lyapunov_epsilon.is_some()),
+            ("divergence_epsilon", params.divergence_epsilon.is_some()),
+            ("reversibility_check", params.reversibility_check),
+        ];
+        if let Some((field, _)) = solver_frames.iter().find(|(_, set)| *set) {
+            let reason = "is not supported with fps".to_string();
+            return Err(SimError::InvalidParam { field, reason });
+        }
+    }
 
-    // 7. Return JSON
-    Ok(HttpResponse::Ok().json(SimResponse {
-        success: true,
-        animation_data: AnimationData {
-            positions,
+    if let Some(section) = &params.poincare_section {
+        check_link("poincare_section.crossing_link", section.crossing_link, params.n)?;
+        check_link("poincare_section.record_link", section.record_link, params.n)?;
+    }
+
+    if let Some(link) = params.phase_link {
+        check_link("phase_link", link, params.n)?;
+    }
+
+    if params.flip_detection && params.n != 2 {
+        let reason = "requires a double pendulum (n = 2)".to_string();
+        return Err(SimError::InvalidParam { field: "flip_detection", reason });
+    }
+
+    if let Some(eps) = params.lyapunov_epsilon {
+        check_positive("lyapunov_epsilon", eps)?;
+    }
+
+    if let Some(eps) = params.divergence_epsilon {
+        check_positive("divergence_epsilon", eps)?;
+        if params.precision != Precision::F64 {
+            let reason = "requires f64 precision".to_string();
+            return Err(SimError::InvalidParam { field: "divergence_epsilon", reason });
+        }
+    }
+
+    if let Some(sep) = params.min_separation {
+        check_positive("min_separation", sep)?;
+    }
+
+    if let Some(max) = params.max_ang_vel {
+        check_positive("max_ang_vel", max)?;
+    }
+
+    if params.error_estimate {
+        let reason = if params.precision != Precision::F64 {
+            Some("requires f64 precision")
+        } else if params.stop_on_collision {
+            Some("cannot be combined with stop_on_collision")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            let reason = reason.to_string();
+            return Err(SimError::InvalidParam { field: "error_estimate", reason });
+        }
+    }
+
+    if let Some(energy) = params.target_energy {
+        let reason = if !energy.is_finite() {
+            Some("must be finite")
+        } else if params.resume_state.is_some() {
+            Some("cannot be combined with resume_state")
+        } else if params.perturbation_sigma.is_some() {
+            Some("cannot be combined with perturbation_sigma (the noise would leave the shell)")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            let reason = reason.to_string();
+            return Err(SimError::InvalidParam { field: "target_energy", reason });
+        }
+    }
+
+    if let Some(sigma) = params.perturbation_sigma {
+        check_positive("perturbation_sigma", sigma)?;
+    }
+
+    // The modes are those of the free chain linearized in uniform gravity
+    if params.normal_mode.is_some() {
+        let reason = if params.resume_state.is_some() {
+            Some("cannot be combined with resume_state")
+        } else if params.target_energy.is_some() {
+            Some("cannot be combined with target_energy")
+        } else if !params.fixed_mask.is_empty() {
+            Some("cannot be combined with fixed_mask")
+        } else if params.gravity_radius.is_some() {
+            Some("requires uniform gravity (no gravity_radius)")
+        } else if params.mode_amplitude.is_none() {
+            Some("requires mode_amplitude")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            let reason = reason.to_string();
+            return Err(SimError::InvalidParam { field: "normal_mode", reason });
+        }
+    }
+    if let Some(amplitude) = params.mode_amplitude {
+        if params.normal_mode.is_none() {
+            let reason = "requires normal_mode".to_string();
+            return Err(SimError::InvalidParam { field: "mode_amplitude", reason });
+        }
+        check_positive("mode_amplitude", amplitude)?;
+    }
+
+    // The closed form covers one free, undamped link whose frames are all the output needs
+    if params.analytic {
+        let reason = if params.n != 1 {
+            Some("requires a single link (n = 1)")
+        } else if params.damping_model != DampingModel::None {
+            Some("cannot be combined with damping_model")
+        } else if !params.fixed_mask.is_empty() {
+            Some("cannot be combined with fixed_mask")
+        } else if params.precision != Precision::F64 {
+            Some("requires f64 precision")
+        } else if params.summary_only {
+            Some("cannot be combined with summary_only")
+        } else if params.gravity_radius.is_some() {
+            Some("requires uniform gravity (no gravity_radius)")
+        } else if params.compute_gravity_work {
+            Some("cannot be combined with compute_gravity_work")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            let reason = reason.to_string();
+            return Err(SimError::InvalidParam { field: "analytic", reason });
+        }
+    }
+
+    // Friction drains energy, so nothing may rely on it being conserved or time-reversible
+    if params.damping_model != DampingModel::None {
+        let conservative = [
+            ("energy_correction", params.energy_correction),
+            ("reversibility_check", params.reversibility_check),
+        ];
+        if let Some((field, _)) = conservative.iter().find(|(_, set)| *set) {
+            let reason = "cannot be combined with damping_model".to_string();
+            return Err(SimError::InvalidParam { field, reason });
+        }
+    }
+
+    if !(params.gx.is_finite() && params.gy.is_finite()) {
+        let reason = "gravity components must be finite".to_string();
+        return Err(SimError::InvalidParam { field: "gx/gy", reason });
+    }
+    if !(params.pivot_x.is_finite() && params.pivot_y.is_finite()) {
+        let reason = "pivot coordinates must be finite".to_string();
+        return Err(SimError::InvalidParam { field: "pivot_x/pivot_y", reason });
+    }
+    let pivot = [params.pivot_x, params.pivot_y];
+    // The frontend scales symmetrically about the origin, so the limit must reach past the
+    // shifted pivot
+    let pivot_reach = params.pivot_x.abs().max(params.pivot_y.abs());
+
+    check_len("masses", &masses, params.n)?;
+    check_len("lengths", &lengths, params.n)?;
+    check_all_positive("masses", &masses)?;
+    check_all_positive("lengths", &lengths)?;
+    // Applied before anything reads the lengths, so the limit follows the scaled chain
+    let length_scale = match params.normalize_total_length {
+        Some(total) => {
+            check_positive("normalize_total_length", total)?;
+            let scale = total / lengths.iter().sum::<f64>();
+            lengths.iter_mut().for_each(|l| *l *= scale);
+            Some(scale)
+        }
+        None => None,
+    };
+    match &params.resume_state {
+        Some(state) => {
+            check_len("resume_state", state, 2 * params.n)?;
+            check_all_finite("resume_state", state)?;
+        }
+        // Initial angles are not needed when resuming from a saved state or generating one
+        None if params.target_energy.is_some() || params.normal_mode.is_some() => {}
+        None => check_len("initial_angles", &angles, params.n)?,
+    }
+
+    // 3. Prepare Physics Vectors (1-based indexing padding)
+    // We prepend 0.0 because the physics logic (math.rs) expects 1-based indices [dummy, m1, m2...]
+    let mut full_masses = vec![0.0];
+    full_masses.extend(&masses);
+
+    let mut full_lengths = vec![0.0];
+    full_lengths.extend(&lengths);
+
+    let mut full_angles = vec![0.0];
+    full_angles.extend(&angles);
+
+    let initial_ang_vels = vec![0.0; params.n + 1]; // Start from rest
+
+    let fixed = if params.fixed_mask.is_empty() {
+        None
+    } else {
+        check_len("fixed_mask", &params.fixed_mask, params.n)?;
+        let mut full_fixed = vec![false];
+        full_fixed.extend(&params.fixed_mask);
+        Some(full_fixed)
+    };
+
+    let damping = parse_damping(params.damping_model, &params.damping, params.n)?;
+
+    // A planet at distance R along the gravity vector, scaled so the pivot still feels (gx, gy)
+    let gravity_model = match params.gravity_radius {
+        None => GravityModel::Uniform,
+        Some(radius) => {
+            check_positive("gravity_radius", radius)?;
+            let g = params.gx.hypot(params.gy);
+            let reach: f64 = lengths.iter().sum();
+            let reason = if !params.gravity_enabled || g == 0.0 {
+                Some("needs gravity enabled with a nonzero (gx, gy)".to_string())
+            } else if radius <= reach {
+                Some(format!("must exceed the chain's reach ({reach} m)"))
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Err(SimError::InvalidParam { field: "gravity_radius", reason });
+            }
+            let center = [params.gx / g * radius, params.gy / g * radius];
+            GravityModel::InverseSquare { center, mu: g * radius * radius }
+        }
+    };
+
+    let topology = parse_topology(&params.topology, params.n)?;
+    let full_parents = match &topology {
+        Some(parents) => parents.clone(),
+        None => (0..=params.n).map(|k| k.saturating_sub(1)).collect(),
+    };
+
+    if params.spring {
+        return run_spring_simulation(
+            params,
+            full_masses,
+            full_lengths,
+            &full_angles,
+            on_progress,
+            deadline,
+        )
+        .map(|data| AnimationData { length_scale, ..data });
+    }
+
+    // 4. Initialize Solver
+    let solver = NPendulumSolver::new(params.n, full_masses, full_lengths.clone())
+        .with_gravity(params.gx, params.gy)
+        .with_rod_inertia(params.rod_inertia)
+        .with_gravity_enabled(params.gravity_enabled)
+        .with_topology(topology)
+        .with_fixed_links(fixed)
+        .with_damping(damping)
+        .with_gravity_model(gravity_model)
+        .with_centripetal_form(params.centripetal_form);
+
+    // 5. Run Simulation
+    // returns the recorded times and state vectors, plus any detected events
+    let collision = params.min_separation.map(|min_separation| CollisionCheck {
+        min_separation,
+        stop: params.stop_on_collision,
+    });
+    let opts = SolveOptions {
+        stride: if params.summary_only || params.final_only {
+            params.n_points - 1
+        } else {
+            params.output_stride
+        },
+        collision,
+        substeps: params.substeps,
+        deadline,
+        energy_correction: params.energy_correction,
+        max_ang_vel: params.max_ang_vel,
+        warmup: Some(params.warmup_time),
+    };
+    let solve_start = Instant::now();
+    let mut y0 = match (&params.resume_state, params.target_energy, params.normal_mode) {
+        (Some(state), _, _) => DVector::from_column_slice(state),
+        (None, Some(energy), _) => {
+            // Nothing lies below the fully hanging configuration
+            let mut hanging = DVector::zeros(2 * params.n);
+            hanging.rows_mut(0, params.n).fill(solver.g_angle);
+            let floor = solver.total_energy(&hanging);
+            if energy <= floor {
+                let reason = format!("must exceed the minimum potential energy {floor} J");
+                return Err(SimError::InvalidParam { field: "target_energy", reason });
+            }
+            solver.sample_at_energy(energy, params.seed)?
+        }
+        (None, None, Some(mode)) => {
+            let amplitude = params.mode_amplitude.map_or(0.0, |a| angle_unit.to_radians(a));
+            solver.normal_mode_state(mode, amplitude)?
+        }
+        (None, None, None) => solver.initial_state(&full_angles, &initial_ang_vels)?,
+    };
+    if let Some(sigma) = params.perturbation_sigma {
+        let sigma = angle_unit.to_radians(sigma);
+        logic::perturb(y0.rows_mut(0, params.n).as_mut_slice(), sigma, params.seed);
+    }
+    let generated = params.target_energy.is_some()
+        || params.perturbation_sigma.is_some()
+        || params.normal_mode.is_some();
+    let initial_state = generated.then(|| y0.as_slice().to_vec());
+    let mut summary = RunSummary::default();
+    // Checked at every integration step, like the summary, so the peak is not missed between
+    // (possibly strided) frames. Steps are dt apart from the start of the recorded run.
+    let step_dt = params.t_max / (params.n_points - 1) as f64;
+    let mut step = 0;
+    let mut fastest_tip: Option<(f64, f64)> = None;
+    let mut gravity_powers = Vec::new();
+    let damped = params.damping_model != DampingModel::None;
+    let mut damping_powers = Vec::new();
+    let mut observe = |y: &DVector<f64>| {
+        if params.summary_only {
+            summary.observe(&solver, y);
+        }
+        if params.compute_tip_speed {
+            let (vx, vy) = solver.bob_velocities(y)[params.n - 1];
+            let t = params.warmup_time + step as f64 * step_dt;
+            fastest_tip = faster_tip(fastest_tip, vx.hypot(vy), t);
+        }
+        if params.compute_gravity_work {
+            gravity_powers.push(solver.gravity_power(y));
+        }
+        if damped {
+            damping_powers.push(solver.damping_power(y));
+        }
+        step += 1;
+    };
+    let exact_period = if params.measure_period { solver.exact_period(&y0) } else { None };
+    // Its own pass at the integrator's step size, so the crossing is found between steps
+    // rather than between (possibly strided) frames
+    let flip_time = if params.flip_detection {
+        let h = params.t_max / (params.n_points - 1) as f64 / params.substeps as f64;
+        solver.flip_time(y0.clone(), 2, params.t_max, h, deadline)?
+    } else {
+        None
+    };
+    let trajectory = match params.precision {
+        Precision::F64 if params.analytic => solver
+            .exact_trajectory(&y0, params.t_max, params.n_points, opts.stride)
+            .ok_or_else(|| SimError::InvalidParam {
+                field: "analytic",
+                reason: "needs a swing below the inverted position (no closed form over the top)"
+                    .to_string(),
+            })?,
+        Precision::F64 => solver.solve_from_state_observed(
+            y0,
+            params.t_max,
+            params.n_points,
+            &opts,
+            on_progress,
+            observe,
+            |_, _, _| ControlFlow::Continue(()),
+        )?,
+        Precision::F32 => {
+            let to_f32 = |v: &[f64]| v.iter().map(|&x| x as f32).collect::<Vec<f32>>();
+            let solver32 = NPendulumSolver::new(
+                params.n,
+                to_f32(&solver.masses),
+                to_f32(&solver.lengths),
+            )
+            .with_gravity(params.gx as f32, params.gy as f32)
+            .with_rod_inertia(params.rod_inertia)
+            .with_gravity_enabled(params.gravity_enabled)
+            .with_topology(solver.parents.clone())
+            .with_fixed_links(solver.fixed.clone())
+            .with_damping(solver.damping.as_ref().map(|d| Damping {
+                model: d.model,
+                coefficients: to_f32(&d.coefficients),
+            }))
+            .with_gravity_model(match solver.gravity_model {
+                GravityModel::Uniform => GravityModel::Uniform,
+                GravityModel::InverseSquare { center, mu } => GravityModel::InverseSquare {
+                    center: center.map(|c| c as f32),
+                    mu: mu as f32,
+                },
+            })
+            .with_centripetal_form(solver.centripetal_form);
+            let opts32 = SolveOptions {
+                stride: opts.stride,
+                substeps: opts.substeps,
+                deadline: opts.deadline,
+                energy_correction: opts.energy_correction,
+                max_ang_vel: opts.max_ang_vel.map(|max| max as f32),
+                warmup: opts.warmup.map(|warmup| warmup as f32),
+                collision: opts.collision.map(|c| CollisionCheck {
+                    min_separation: c.min_separation as f32,
+                    stop: c.stop,
+                }),
+            };
+            let traj32 = solver32.solve_from_state_observed(
+                y0.cast::<f32>(),
+                params.t_max as f32,
+                params.n_points,
+                &opts32,
+                on_progress,
+                |y| observe(&y.map(f64::from)),
+                |_, _, _| ControlFlow::Continue(()),
+            )?;
+            // Widen for post-processing; the f32 frames are dropped as we go
+            Trajectory {
+                t_axis: traj32.t_axis.into_iter().map(f64::from).collect(),
+                sol: traj32.sol.into_iter().map(|y| y.cast::<f64>()).collect(),
+                first_collision_time: traj32.first_collision_time.map(f64::from),
+                blew_up_at_time: traj32.blew_up_at_time.map(f64::from),
+            }
+        }
+    };
+    let solve_millis = solve_start.elapsed().as_secs_f64() * 1000.0;
+    // The closed form has no integration steps to observe, so take the peak over its frames
+    if params.compute_tip_speed && params.analytic {
+        for (y, &t) in trajectory.sol.iter().zip(&trajectory.t_axis) {
+            let (vx, vy) = solver.bob_velocities(y)[params.n - 1];
+            fastest_tip = faster_tip(fastest_tip, vx.hypot(vy), t);
+        }
+    }
+    let (max_tip_speed, max_tip_speed_time) = fastest_tip.unzip();
+    let gravity_work = params
+        .compute_gravity_work
+        .then(|| logic::integrate(&gravity_powers, step_dt, params.quadrature));
+    let dissipated_energy =
+        damped.then(|| -logic::integrate(&damping_powers, step_dt, params.quadrature));
+    // Unstable before the first frame: nothing worth returning
+    if let (true, Some(time)) = (trajectory.sol.is_empty(), trajectory.blew_up_at_time) {
+        return Err(SimError::BlewUp { time });
+    }
+    // Calculate display limit (Total length + padding)
+    let limit: f64 = pivot_reach + lengths.iter().sum::<f64>() + 0.5;
+
+    // Richardson: RK4's global error scales as dt⁴, so with y_h and y_{h/2} the error of the
+    // finer run is ≈ |y_h − y_{h/2}| / (2⁴ − 1), and the coarse run's ≈ 16x that, i.e.
+    // |y_h − y_{h/2}| · 16/15. Halving dt is done with doubled substeps so the frames align.
+    let error_estimate = if params.error_estimate {
+        let fine_opts = SolveOptions {
+            stride: params.n_points - 1,
+            substeps: 2 * params.substeps,
+            warmup: None,
+            ..opts.clone()
+        };
+        let y0 = trajectory.sol[0].clone();
+        let fine = solver.solve_from_state(y0, params.t_max, params.n_points, &fine_opts)?;
+        let coarse_end = &trajectory.sol[trajectory.sol.len() - 1];
+        Some((coarse_end - &fine.sol[fine.sol.len() - 1]).norm() * 16.0 / 15.0)
+    } else {
+        None
+    };
+
+    if params.summary_only {
+        let final_state = trajectory.sol.last().map(|y| y.as_slice().to_vec()).unwrap_or_default();
+        return Ok(AnimationData {
             n: params.n,
             limit,
+            solve_millis: Some(solve_millis),
+            final_state,
+            first_collision_time: trajectory.first_collision_time,
+            blew_up_at_time: trajectory.blew_up_at_time,
+            summary: Some(summary),
+            error_estimate,
+            initial_state,
+            flip_time,
+            max_tip_speed,
+            max_tip_speed_time,
+            gravity_work,
+            dissipated_energy,
+            length_scale,
+            ..AnimationData::default()
+        });
+    }
+
+    // Positions at the requested times, interpolated between the recorded frames
+    let sampled_positions = match &params.sample_times {
+        Some(times) => {
+            let (t_start, t_end) = (params.warmup_time, params.warmup_time + params.t_max);
+            let states = times
+                .iter()
+                .map(|&t| {
+                    if !(t_start.min(t_end)..=t_start.max(t_end)).contains(&t) {
+                        return None;
+                    }
+                    trajectory.state_at(t)
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| SimError::InvalidParam {
+                    field: "sample_times",
+                    reason: format!("every time must lie between {t_start} and {t_end}"),
+                })?;
+            let mut sampled = compute_positions(&states, params.n, &full_lengths, &full_parents)?;
+            translate_positions(&mut sampled, pivot);
+            Some(sampled)
+        }
+        None => None,
+    };
+    let period = if params.measure_period {
+        solver.measure_period(&trajectory)?
+    } else {
+        None
+    };
+    // Energy reference from the first frame, before `final_only` drops it
+    let e0 = solver.total_energy(&trajectory.sol[0]);
+    let trajectory = match params.fps {
+        Some(fps) => trajectory.resample(fps),
+        None => trajectory,
+    };
+    let Trajectory { mut t_axis, mut sol, first_collision_time, blew_up_at_time } = trajectory;
+    if params.final_only {
+        let last = sol.len() - 1;
+        t_axis.drain(..last);
+        sol.drain(..last);
+    }
+
+    // 6. Post-Process Results
+    // Convert angles to Cartesian coordinates for the frontend
+    let mut positions = compute_positions(&sol, params.n, &full_lengths, &full_parents)?;
+    translate_positions(&mut positions, pivot);
+    let center_of_mass = if params.rod_inertia {
+        compute_center_of_mass(&rod_midpoints(&positions, &full_parents, pivot), &masses)
+    } else {
+        compute_center_of_mass(&positions, &masses)
+    };
+    let angular_momentum = sol.iter().map(|y| solver.angular_momentum(y)).collect();
+    let energy_drift = sol.iter().map(|y| (solver.total_energy(y) - e0).abs()).fold(0.0, f64::max);
+    let velocity_bounds = compute_velocity_bounds(&sol, params.n, params.n);
+    let phase_space = params.phase_link.map(|link| compute_phase_space(&sol, link, params.n));
+    let tip_path = params.tip_trace.then(|| compute_tip_path(&positions));
+    let tensions = if params.compute_tensions {
+        Some(sol.iter().map(|y| solver.tensions(y)).collect::<Result<Vec<_>, _>>()?)
+    } else {
+        None
+    };
+    let (lagrangian, hamiltonian) = if params.compute_lagrangian {
+        let (l, h) = sol.iter().map(|y| solver.lagrangian_and_hamiltonian(y)).unzip();
+        (Some(l), Some(h))
+    } else {
+        (None, None)
+    };
+    let condition_number = params
+        .compute_condition_number
+        .then(|| sol.iter().map(|y| solver.mass_matrix_condition(y)).collect());
+    // Time derivative of `positions`, built along the same pivot-to-bob paths
+    let bob_velocities = params.compute_velocities.then(|| {
+        sol.iter()
+            .map(|y| solver.bob_velocities(y).into_iter().flat_map(|(vx, vy)| [vx, vy]).collect())
+            .collect()
+    });
+    let final_state = sol.last().map(|y| y.as_slice().to_vec()).unwrap_or_default();
+    let poincare_points = params.poincare_section.as_ref().map(|section| {
+        logic::poincare_section(
+            &sol,
+            params.n,
+            section.crossing_link,
+            section.record_link,
+            section.direction,
+        )
+    });
+    let lyapunov = match params.lyapunov_epsilon {
+        Some(eps) => {
+            let (running, exponent) =
+                solver.lyapunov_exponent(&sol[0], params.t_max, params.n_points, eps, &opts)?;
+            Some(LyapunovEstimate { running, exponent })
+        }
+        None => None,
+    };
+    // Butterfly effect: rerun from θ1 + ε (without collision stops, so the frames line up)
+    // and measure how far apart the two state vectors drift.
+    let divergence = match params.divergence_epsilon {
+        Some(eps) => {
+            let mut perturbed = sol[0].clone();
+            perturbed[0] += eps;
+            let companion_opts = SolveOptions { collision: None, warmup: None, ..opts.clone() };
+            let companion =
+                solver.solve_from_state(perturbed, params.t_max, params.n_points, &companion_opts)?;
+            Some(sol.iter().zip(&companion.sol).map(|(a, b)| (a - b).norm()).collect())
+        }
+        None => None,
+    };
+    // Time-reversal check: flip the final velocities, integrate for the same duration and
+    // compare the angles we land on with the initial ones.
+    let reversibility_residual = if params.reversibility_check {
+        let n = params.n;
+        let mut reversed = sol[sol.len() - 1].clone();
+        reversed.rows_mut(n, n).neg_mut();
+        let back_opts = SolveOptions {
+            stride: params.n_points - 1,
+            collision: None,
+            substeps: params.substeps,
+            deadline,
+            energy_correction: params.energy_correction,
+            max_ang_vel: params.max_ang_vel,
+            warmup: None,
+        };
+        let back = solver.solve_from_state(reversed, params.t_max, params.n_points, &back_opts)?;
+        Some((back.sol[back.sol.len() - 1].rows(0, n) - sol[0].rows(0, n)).norm())
+    } else {
+        None
+    };
+
+    // 7. Return the animation payload
+    Ok(AnimationData {
+        positions: Positions::Nested(positions),
+        times: t_axis,
+        stride: None,
+        frame_count: None,
+        n: params.n,
+        limit,
+        solve_millis: Some(solve_millis),
+        final_state,
+        center_of_mass,
+        angular_momentum,
+        velocity_bounds,
+        poincare_points,
+        phase_space,
+        lyapunov,
+        divergence,
+        reversibility_residual,
+        first_collision_time,
+        blew_up_at_time,
+        sampled_positions,
+        period,
+        exact_period,
+        flip_time,
+        max_tip_speed,
+        max_tip_speed_time,
+        gravity_work,
+        dissipated_energy,
+        length_scale,
+        tip_path,
+        tensions,
+        lagrangian,
+        hamiltonian,
+        condition_number,
+        bob_velocities,
+        link_lengths: None,
+        energy_drift: Some(energy_drift),
+        summary: None,
+        error_estimate,
+        initial_state,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct EquilibriumParams {
+    n: usize,                       // Number of pendulums
+    masses: String,                 // Comma-separated masses
+    lengths: String,                // Comma-separated lengths
+    #[serde(default)]
+    guess: Option<String>,          // Comma-separated near-equilibrium angles (degrees) to refine
+}
+
+#[derive(Serialize)]
+struct EquilibriumResponse {
+    success: bool,
+    fixed_points: Vec<FixedPoint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refined: Option<Vec<f64>>,      // Newton-refined guess (degrees)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<SimError>,
+}
+
+#[derive(Serialize)]
+struct FixedPoint {
+    angles: Vec<f64>, // Degrees, directly usable as `initial_angles`
+    stable: bool,
+}
+
+#[derive(Deserialize)]
+pub struct JacobianParams {
+    n: usize,                       // Number of pendulums
+    masses: String,                 // Comma-separated masses
+    lengths: String,                // Comma-separated lengths
+    state: Vec<f64>,                // Full [θ1..θn, ω1..ωn] state (radians) to linearize around
+    #[serde(default)]
+    gx: f64,                        // Gravity x-component (m/s²)
+    #[serde(default = "default_gy")]
+    gy: f64,                        // Gravity y-component (m/s²)
+    #[serde(default)]
+    eigenvalues: bool,              // Also return the Jacobian's eigenvalues
+    #[serde(default)]
+    rod_inertia: bool,              // Uniform rods instead of point masses
+}
+
+#[derive(Serialize)]
+struct JacobianResponse {
+    success: bool,
+    jacobian: Vec<Vec<f64>>,        // Row-major ∂(dy/dt)/∂y, 2n x 2n
+    #[serde(skip_serializing_if = "Option::is_none")]
+    eigenvalues: Option<Vec<[f64; 2]>>, // (re, im) pairs, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<SimError>,
+}
+
+#[derive(Deserialize)]
+pub struct EffectivePotentialParams {
+    n: usize,                       // Number of pendulums
+    masses: String,                 // Comma-separated masses
+    lengths: String,                // Comma-separated lengths
+    drive_amplitude: f64,           // Vertical pivot oscillation amplitude (m)
+    drive_frequency: f64,           // Pivot oscillation frequency (rad/s)
+    #[serde(default = "default_potential_samples")]
+    samples: usize,                 // Number of angles sampled over [-180°, 180°]
+}
+
+#[derive(Serialize)]
+struct EffectivePotentialResponse {
+    success: bool,
+    angles: Vec<f64>,               // Common angle of all links (degrees)
+    potential: Vec<f64>,            // Effective potential at each angle (J)
+    inverted_stable: bool,          // Whether the drive stabilizes the fully inverted state
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<SimError>,
+}
+
+#[derive(Deserialize)]
+pub struct ConvergenceParams {
+    params: SimParams,              // Base scenario; n_points, dt and output_stride are overridden
+    resolutions: Vec<usize>,        // n_points values to compare; the largest is the reference
+}
+
+#[derive(Serialize)]
+struct ConvergenceResponse {
+    success: bool,
+    rows: Vec<ConvergenceRow>,      // One per distinct resolution, ascending
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<SimError>,
+}
+
+#[derive(Serialize)]
+struct ConvergenceRow {
+    n_points: usize,
+    error: f64,                     // |final state − reference final state| (0 for the reference)
+    solve_millis: f64,              // Wall-clock time of the integration alone (ms)
+}
+
+/// Most resolutions a single convergence study may run.
+const MAX_RESOLUTIONS: usize = 8;
+
+#[derive(Deserialize)]
+pub struct AccelerationParams {
+    n: usize,                       // Number of pendulums
+    masses: String,                 // Comma-separated masses
+    lengths: String,                // Comma-separated lengths
+    state: Vec<f64>,                // Full [θ1..θn, ω1..ωn] state (radians)
+    #[serde(default)]
+    gx: f64,                        // Gravity x-component (m/s²)
+    #[serde(default = "default_gy")]
+    gy: f64,                        // Gravity y-component (m/s²)
+    #[serde(default)]
+    rod_inertia: bool,              // Uniform rods instead of point masses
+}
+
+#[derive(Serialize)]
+struct AccelerationResponse {
+    success: bool,
+    accelerations: Vec<f64>,        // [α1..αn] (rad/s²)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<SimError>,
+}
+
+#[derive(Deserialize)]
+pub struct FlipMapParams {
+    masses: String,                 // Comma-separated masses (two links)
+    lengths: String,                // Comma-separated lengths (two links)
+    theta1_range: [f64; 2],         // [min, max] initial θ1 (degrees)
+    theta2_range: [f64; 2],         // [min, max] initial θ2 (degrees)
+    resolution: usize,              // Grid points per axis
+    t_max: f64,                     // How long to wait for a flip (s)
+    #[serde(default = "default_flip_dt")]
+    dt: f64,                        // Integration step (s)
+}
+
+#[derive(Serialize)]
+struct FlipMapResponse {
+    success: bool,
+    theta1: Vec<f64>,               // Grid θ1 values (degrees)
+    theta2: Vec<f64>,               // Grid θ2 values (degrees)
+    flip_times: Vec<Vec<Option<f64>>>, // [i][j]: first time |θ2| > 180° from (θ1_i, θ2_j), or null
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<SimError>,
+}
+
+/// Largest grid (points per axis) a flip map may request.
+const MAX_FLIP_RESOLUTION: usize = 256;
+
+#[derive(Deserialize)]
+pub struct EigenfrequencyParams {
+    n: usize,                       // Number of pendulums
+    masses: String,                 // Comma-separated masses
+    lengths: String,                // Comma-separated lengths
+    #[serde(default)]
+    gx: f64,                        // Gravity x-component (m/s²)
+    #[serde(default = "default_gy")]
+    gy: f64,                        // Gravity y-component (m/s²)
+    #[serde(default)]
+    rod_inertia: bool,              // Uniform rods instead of point masses
+}
+
+#[derive(Serialize)]
+struct EigenfrequencyResponse {
+    success: bool,
+    frequencies: Vec<f64>,          // Normal-mode angular frequencies (rad/s), ascending
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<SimError>,
+}
+
+/// Largest n for which all 2^n fixed points are enumerated.
+const MAX_EQUILIBRIUM_N: usize = 10;
+
+/// Elastic-link prototype: integrates the spring chain and returns the base trajectory outputs
+/// plus per-frame link lengths and the energy drift (a check on the integration).
+fn run_spring_simulation(
+    params: &SimParams,
+    full_masses: Vec<f64>,
+    full_lengths: Vec<f64>,
+    full_angles: &[f64],
+    on_progress: &mut dyn FnMut(f64),
+    deadline: Option<Deadline>,
+) -> Result<AnimationData, SimError> {
+    let n = params.n;
+    let unsupported = [
+        ("resume_state", params.resume_state.is_some()),
+        ("poincare_section", params.poincare_section.is_some()),
+        ("lyapunov_epsilon", params.lyapunov_epsilon.is_some()),
+        ("divergence_epsilon", params.divergence_epsilon.is_some()),
+        ("reversibility_check", params.reversibility_check),
+        ("precision", params.precision != Precision::F64),
+        ("min_separation", params.min_separation.is_some()),
+        ("sample_times", params.sample_times.is_some()),
+        ("fps", params.fps.is_some()),
+        ("measure_period", params.measure_period),
+        ("flip_detection", params.flip_detection),
+        ("rod_inertia", params.rod_inertia),
+        ("topology", !params.topology.trim().is_empty()),
+        ("compute_tensions", params.compute_tensions),
+        ("compute_lagrangian", params.compute_lagrangian),
+        ("compute_condition_number", params.compute_condition_number),
+        ("compute_tip_speed", params.compute_tip_speed),
+        ("compute_gravity_work", params.compute_gravity_work),
+        ("energy_correction", params.energy_correction),
+        ("summary_only", params.summary_only),
+        ("final_only", params.final_only),
+        ("error_estimate", params.error_estimate),
+        ("fixed_mask", !params.fixed_mask.is_empty()),
+        ("target_energy", params.target_energy.is_some()),
+        ("perturbation_sigma", params.perturbation_sigma.is_some()),
+        ("normal_mode", params.normal_mode.is_some()),
+        ("damping_model", params.damping_model != DampingModel::None),
+        ("analytic", params.analytic),
+        ("gravity_radius", params.gravity_radius.is_some()),
+        ("warmup_time", params.warmup_time > 0.0),
+        ("centripetal_form", params.centripetal_form != CentripetalForm::Direct),
+    ];
+    if let Some((field, _)) = unsupported.iter().find(|(_, set)| *set) {
+        let reason = "is not supported in spring mode".to_string();
+        return Err(SimError::InvalidParam { field, reason });
+    }
+
+    let stiffness = parse_csv_f64("stiffness", &params.stiffness)?;
+    check_len("stiffness", &stiffness, n)?;
+    check_all_positive("stiffness", &stiffness)?;
+    let mut full_stiffness = vec![0.0];
+    full_stiffness.extend(&stiffness);
+
+    let (gx, gy) = if params.gravity_enabled { (params.gx, params.gy) } else { (0.0, 0.0) };
+    let solver = SpringPendulumSolver::new(n, full_masses, full_lengths, full_stiffness)
+        .with_gravity(gx, gy);
+    let opts = SolveOptions {
+        stride: params.output_stride,
+        collision: None,
+        substeps: params.substeps,
+        deadline,
+        energy_correction: false,
+        max_ang_vel: params.max_ang_vel,
+        warmup: None,
+    };
+    let y0 = solver.initial_state(full_angles)?;
+    let solve_start = Instant::now();
+    let Trajectory { t_axis, sol, blew_up_at_time, .. } =
+        solver.solve_from_state_with_progress(y0, params.t_max, params.n_points, &opts, on_progress)?;
+    let solve_millis = solve_start.elapsed().as_secs_f64() * 1000.0;
+    if let (true, Some(time)) = (sol.is_empty(), blew_up_at_time) {
+        return Err(SimError::BlewUp { time });
+    }
+
+    let models: Vec<_> = sol.iter().map(|y| solver.math_at(y)).collect();
+    let mut positions: Vec<Vec<f64>> = models.iter().map(|m| m.bob_positions()).collect();
+    let masses = &solver.masses[1..];
+    let angular_momentum = models
+        .iter()
+        .zip(&positions)
+        .map(|(model, pos)| {
+            let vel = model.bob_velocities();
+            (0..n)
+                .map(|i| masses[i] * (pos[2 * i] * vel[2 * i + 1] - pos[2 * i + 1] * vel[2 * i]))
+                .sum()
+        })
+        .collect();
+
+    let e0 = models[0].total_energy();
+    let energy_drift = models.iter().map(|m| (m.total_energy() - e0).abs()).fold(0.0, f64::max);
+
+    // Springs stretch, so scale to the furthest bob actually reached
+    let reach = positions
+        .iter()
+        .flat_map(|p| p.chunks_exact(2).map(|b| b[0].hypot(b[1])))
+        .fold(0.0, f64::max);
+    let limit = params.pivot_x.abs().max(params.pivot_y.abs()) + reach + 0.5;
+
+    // Angular momentum and reach are about the pivot; the remaining outputs are shifted
+    translate_positions(&mut positions, [params.pivot_x, params.pivot_y]);
+    let center_of_mass = compute_center_of_mass(&positions, masses);
+    let tip_path = params.tip_trace.then(|| compute_tip_path(&positions));
+
+    Ok(AnimationData {
+        positions: Positions::Nested(positions),
+        times: t_axis,
+        n,
+        limit,
+        solve_millis: Some(solve_millis),
+        final_state: sol.last().map(|y| y.as_slice().to_vec()).unwrap_or_default(),
+        center_of_mass,
+        angular_momentum,
+        velocity_bounds: compute_velocity_bounds(&sol, n, 2 * n),
+        phase_space: params.phase_link.map(|link| compute_phase_space(&sol, link, 2 * n)),
+        tip_path,
+        bob_velocities: params
+            .compute_velocities
+            .then(|| models.iter().map(|m| m.bob_velocities().as_slice().to_vec()).collect()),
+        link_lengths: Some(sol.iter().map(|y| y.rows(n, n).iter().copied().collect()).collect()),
+        energy_drift: Some(energy_drift),
+        blew_up_at_time,
+        ..AnimationData::default()
+    })
+}
+
+/// Convergence Handler: runs one scenario at several resolutions and reports each run's
+/// final-state error against the finest one, alongside its cost. All runs share a single
+/// `SolveTimeout` budget.
+#[cfg(feature = "server")]
+pub async fn convergence_handler(
+    study: web::Json<ConvergenceParams>,
+    timeout: web::Data<SolveTimeout>,
+) -> Result<HttpResponse> {
+    let study = study.into_inner();
+    let deadline = timeout.deadline();
+    let run = web::block(move || run_convergence_study(&study, deadline));
+    let result = match rt::time::timeout(timeout.0, run).await {
+        Ok(result) => result?,
+        Err(_) => Err(SimError::TimedOut { limit_secs: deadline.limit_secs }),
+    };
+    let response = match result {
+        Ok(rows) => ConvergenceResponse { success: true, rows, error: None },
+        Err(err) => ConvergenceResponse { success: false, rows: Vec::new(), error: Some(err) },
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Runs the base scenario at every distinct resolution (ascending), recording only the first
+/// and last frames, and compares each final state with the finest run's.
+fn run_convergence_study(
+    study: &ConvergenceParams,
+    deadline: Deadline,
+) -> Result<Vec<ConvergenceRow>, SimError> {
+    let mut resolutions = study.resolutions.clone();
+    resolutions.sort_unstable();
+    resolutions.dedup();
+    if resolutions.len() < 2 {
+        let reason = "needs at least two distinct values".to_string();
+        return Err(SimError::InvalidParam { field: "resolutions", reason });
+    }
+    if resolutions.len() > MAX_RESOLUTIONS {
+        return Err(SimError::TooLarge {
+            field: "resolutions",
+            max: MAX_RESOLUTIONS,
+            got: resolutions.len(),
+        });
+    }
+    // A truncated run ends at a resolution-dependent time, so its final state is not comparable
+    if study.params.stop_on_collision {
+        let reason = "cannot be used in a convergence study".to_string();
+        return Err(SimError::InvalidParam { field: "stop_on_collision", reason });
+    }
+
+    let runs = resolutions
+        .iter()
+        .map(|&n_points| {
+            let params = SimParams {
+                n_points,
+                dt: None,
+                output_stride: n_points.saturating_sub(1).max(1),
+                ..study.params.clone()
+            };
+            let data = run_simulation(&params, &mut |_| {}, Some(deadline))?;
+            if let Some(time) = data.blew_up_at_time {
+                return Err(SimError::BlewUp { time });
+            }
+            let final_state = DVector::from_vec(data.final_state);
+            Ok((n_points, final_state, data.solve_millis.unwrap_or_default()))
+        })
+        .collect::<Result<Vec<_>, SimError>>()?;
+
+    let (_, reference, _) = &runs[runs.len() - 1];
+    Ok(runs
+        .iter()
+        .map(|(n_points, state, solve_millis)| ConvergenceRow {
+            n_points: *n_points,
+            error: (state - reference).norm(),
+            solve_millis: *solve_millis,
+        })
+        .collect())
+}
+
+/// Flip Map Handler: the double-pendulum "fractal" chaos map. Every cell of a grid of initial
+/// (θ1, θ2) starts from rest and reports when the second link first flips over. Rows run in
+/// parallel and share a single `SolveTimeout` budget.
+#[cfg(feature = "server")]
+pub async fn flip_map_handler(
+    params: web::Json<FlipMapParams>,
+    timeout: web::Data<SolveTimeout>,
+) -> Result<HttpResponse> {
+    let params = params.into_inner();
+    let deadline = timeout.deadline();
+    let run = web::block(move || compute_flip_map(&params, deadline));
+    let result = match rt::time::timeout(timeout.0, run).await {
+        Ok(result) => result?,
+        Err(_) => Err(SimError::TimedOut { limit_secs: deadline.limit_secs }),
+    };
+    let response = match result {
+        Ok(FlipMap { theta1, theta2, flip_times }) => {
+            FlipMapResponse { success: true, theta1, theta2, flip_times, error: None }
+        }
+        Err(err) => FlipMapResponse {
+            success: false,
+            theta1: Vec::new(),
+            theta2: Vec::new(),
+            flip_times: Vec::new(),
+            error: Some(err),
         },
-        message: None,
-    }))
-}
\ No newline at end of file
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Grid axes (degrees) and the flip time of every cell.
+struct FlipMap {
+    theta1: Vec<f64>,
+    theta2: Vec<f64>,
+    flip_times: Vec<Vec<Option<f64>>>,
+}
+
+fn compute_flip_map(params: &FlipMapParams, deadline: Deadline) -> Result<FlipMap, SimError> {
+    let masses = parse_csv_f64("masses", &params.masses)?;
+    let lengths = parse_csv_f64("lengths", &params.lengths)?;
+    check_len("masses", &masses, 2)?;
+    check_len("lengths", &lengths, 2)?;
+    check_all_positive("masses", &masses)?;
+    check_all_positive("lengths", &lengths)?;
+    check_all_finite("theta1_range", &params.theta1_range)?;
+    check_all_finite("theta2_range", &params.theta2_range)?;
+    if params.resolution < 2 {
+        let reason = "must be at least 2".to_string();
+        return Err(SimError::InvalidParam { field: "resolution", reason });
+    }
+    if params.resolution > MAX_FLIP_RESOLUTION {
+        return Err(SimError::TooLarge {
+            field: "resolution",
+            max: MAX_FLIP_RESOLUTION,
+            got: params.resolution,
+        });
+    }
+    // Same bound on the steps per cell as on a single /simulate run
+    check_positive("t_max", params.t_max)?;
+    points_for_dt(params.t_max, params.dt)?;
+
+    let axis = |[lo, hi]: [f64; 2]| -> Vec<f64> {
+        let last = (params.resolution - 1) as f64;
+        (0..params.resolution).map(|i| lo + (hi - lo) * i as f64 / last).collect()
+    };
+    let theta1 = axis(params.theta1_range);
+    let theta2 = axis(params.theta2_range);
+
+    let flip_times = theta1
+        .par_iter()
+        .map(|&t1| {
+            // The solver keeps per-instance scratch space, so every row builds its own
+            let solver = NPendulumSolver::new(
+                2,
+                vec![0.0, masses[0], masses[1]],
+                vec![0.0, lengths[0], lengths[1]],
+            );
+            theta2
+                .iter()
+                .map(|&t2| {
+                    let y0 = DVector::from_vec(vec![t1.to_radians(), t2.to_radians(), 0.0, 0.0]);
+                    solver.flip_time(y0, 2, params.t_max, params.dt, Some(deadline))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(FlipMap { theta1, theta2, flip_times })
+}
+
+/// Equilibria Handler: lists the hanging/inverted fixed points and optionally refines a guess.
+#[cfg(feature = "server")]
+pub async fn equilibria_handler(params: web::Json<EquilibriumParams>) -> Result<HttpResponse> {
+    let response = match find_equilibria(&params) {
+        Ok((fixed_points, refined)) => EquilibriumResponse {
+            success: true,
+            fixed_points,
+            refined,
+            error: None,
+        },
+        Err(err) => EquilibriumResponse {
+            success: false,
+            fixed_points: Vec::new(),
+            refined: None,
+            error: Some(err),
+        },
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Returns the enumerated fixed points and, if a guess was supplied, its refinement.
+fn find_equilibria(
+    params: &EquilibriumParams,
+) -> Result<(Vec<FixedPoint>, Option<Vec<f64>>), SimError> {
+    if params.n == 0 {
+        return Err(SimError::InvalidParam { field: "n", reason: "must be at least 1".to_string() });
+    }
+    if params.n > MAX_EQUILIBRIUM_N {
+        return Err(SimError::TooLarge { field: "n", max: MAX_EQUILIBRIUM_N, got: params.n });
+    }
+
+    let masses = parse_csv_f64("masses", &params.masses)?;
+    let lengths = parse_csv_f64("lengths", &params.lengths)?;
+    check_len("masses", &masses, params.n)?;
+    check_len("lengths", &lengths, params.n)?;
+    check_all_positive("masses", &masses)?;
+    check_all_positive("lengths", &lengths)?;
+
+    let mut full_masses = vec![0.0];
+    full_masses.extend(&masses);
+    let mut full_lengths = vec![0.0];
+    full_lengths.extend(&lengths);
+    let solver = NPendulumSolver::new(params.n, full_masses, full_lengths);
+
+    let to_degrees = |angles: &DVector<f64>| angles.iter().map(|a| a.to_degrees()).collect();
+    let fixed_points = solver
+        .fixed_points()
+        .iter()
+        .map(|(angles, stable)| FixedPoint { angles: to_degrees(angles), stable: *stable })
+        .collect();
+
+    let refined = match &params.guess {
+        Some(guess) => {
+            let guess_deg = parse_csv_f64("guess", guess)?;
+            check_len("guess", &guess_deg, params.n)?;
+            let guess_rad: Vec<f64> = guess_deg.iter().map(|d| d.to_radians()).collect();
+            Some(to_degrees(&solver.refine_equilibrium(&guess_rad)?))
+        }
+        None => None,
+    };
+
+    Ok((fixed_points, refined))
+}
+
+/// Effective Potential Handler: the Kapitza averaged potential of a vertically driven pivot,
+/// sampled along the straight-chain configurations, plus the inverted-state stability verdict.
+#[cfg(feature = "server")]
+pub async fn effective_potential_handler(
+    params: web::Json<EffectivePotentialParams>,
+) -> Result<HttpResponse> {
+    let response = match compute_effective_potential(&params) {
+        Ok((angles, potential, inverted_stable)) => EffectivePotentialResponse {
+            success: true,
+            angles,
+            potential,
+            inverted_stable,
+            error: None,
+        },
+        Err(err) => EffectivePotentialResponse {
+            success: false,
+            angles: Vec::new(),
+            potential: Vec::new(),
+            inverted_stable: false,
+            error: Some(err),
+        },
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Sampled angles (degrees), the effective potential at each, and the inverted-state verdict.
+type EffectivePotentialCurve = (Vec<f64>, Vec<f64>, bool);
+
+/// Samples the effective potential with every link at the same angle θ ∈ [-π, π].
+fn compute_effective_potential(
+    params: &EffectivePotentialParams,
+) -> Result<EffectivePotentialCurve, SimError> {
+    if params.n == 0 {
+        return Err(SimError::InvalidParam { field: "n", reason: "must be at least 1".to_string() });
+    }
+    if params.n > MAX_EQUILIBRIUM_N {
+        return Err(SimError::TooLarge { field: "n", max: MAX_EQUILIBRIUM_N, got: params.n });
+    }
+    if params.samples < 2 {
+        let reason = "must be at least 2".to_string();
+        return Err(SimError::InvalidParam { field: "samples", reason });
+    }
+    let (amplitude, frequency) = (params.drive_amplitude, params.drive_frequency);
+    check_positive("drive_amplitude", amplitude)?;
+    check_positive("drive_frequency", frequency)?;
+
+    let masses = parse_csv_f64("masses", &params.masses)?;
+    let lengths = parse_csv_f64("lengths", &params.lengths)?;
+    check_len("masses", &masses, params.n)?;
+    check_len("lengths", &lengths, params.n)?;
+    check_all_positive("masses", &masses)?;
+    check_all_positive("lengths", &lengths)?;
+
+    let mut full_masses = vec![0.0];
+    full_masses.extend(&masses);
+    let mut full_lengths = vec![0.0];
+    full_lengths.extend(&lengths);
+    let solver = NPendulumSolver::new(params.n, full_masses, full_lengths);
+
+    let step = 2.0 * std::f64::consts::PI / (params.samples - 1) as f64;
+    let thetas: Vec<f64> =
+        (0..params.samples).map(|i| -std::f64::consts::PI + i as f64 * step).collect();
+    let potential = thetas
+        .iter()
+        .map(|&theta| solver.effective_potential(&vec![theta; params.n], amplitude, frequency))
+        .collect::<Result<Vec<_>, _>>()?;
+    let inverted_stable = solver.inverted_stabilized(amplitude, frequency)?;
+
+    Ok((thetas.iter().map(|t| t.to_degrees()).collect(), potential, inverted_stable))
+}
+
+/// Jacobian Handler: linearizes the equations of motion around a state for stability analysis.
+#[cfg(feature = "server")]
+pub async fn jacobian_handler(params: web::Json<JacobianParams>) -> Result<HttpResponse> {
+    let response = match linearize(&params) {
+        Ok((jacobian, eigenvalues)) => JacobianResponse {
+            success: true,
+            jacobian,
+            eigenvalues,
+            error: None,
+        },
+        Err(err) => JacobianResponse {
+            success: false,
+            jacobian: Vec::new(),
+            eigenvalues: None,
+            error: Some(err),
+        },
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Row-major Jacobian plus its optional (re, im) eigenvalues.
+type Linearization = (Vec<Vec<f64>>, Option<Vec<[f64; 2]>>);
+
+/// Returns the finite-difference Jacobian (row-major) and, if requested, its eigenvalues.
+fn linearize(params: &JacobianParams) -> Result<Linearization, SimError> {
+    if params.n == 0 {
+        return Err(SimError::InvalidParam { field: "n", reason: "must be at least 1".to_string() });
+    }
+    if params.n > MAX_N {
+        return Err(SimError::TooLarge { field: "n", max: MAX_N, got: params.n });
+    }
+
+    let masses = parse_csv_f64("masses", &params.masses)?;
+    let lengths = parse_csv_f64("lengths", &params.lengths)?;
+    check_len("masses", &masses, params.n)?;
+    check_len("lengths", &lengths, params.n)?;
+    check_all_positive("masses", &masses)?;
+    check_all_positive("lengths", &lengths)?;
+    check_len("state", &params.state, 2 * params.n)?;
+    check_all_finite("state", &params.state)?;
+    if !(params.gx.is_finite() && params.gy.is_finite()) {
+        let reason = "gravity components must be finite".to_string();
+        return Err(SimError::InvalidParam { field: "gx/gy", reason });
+    }
+
+    let mut full_masses = vec![0.0];
+    full_masses.extend(&masses);
+    let mut full_lengths = vec![0.0];
+    full_lengths.extend(&lengths);
+    let solver = NPendulumSolver::new(params.n, full_masses, full_lengths)
+        .with_gravity(params.gx, params.gy)
+        .with_rod_inertia(params.rod_inertia);
+
+    let jac = solver.jacobian(0.0, &DVector::from_column_slice(&params.state))?;
+    let eigenvalues = params
+        .eigenvalues
+        .then(|| jac.complex_eigenvalues().iter().map(|z| [z.re, z.im]).collect());
+    let rows = jac.row_iter().map(|row| row.iter().copied().collect()).collect();
+
+    Ok((rows, eigenvalues))
+}
+
+/// Eigenfrequencies Handler: normal-mode frequencies of small oscillations about the hanging
+/// equilibrium, e.g. `/eigenfrequencies?n=2&masses=1,1&lengths=1,1`.
+#[cfg(feature = "server")]
+pub async fn eigenfrequencies_handler(
+    params: web::Query<EigenfrequencyParams>,
+) -> Result<HttpResponse> {
+    let response = match compute_eigenfrequencies(&params) {
+        Ok(frequencies) => EigenfrequencyResponse { success: true, frequencies, error: None },
+        Err(err) => EigenfrequencyResponse {
+            success: false,
+            frequencies: Vec::new(),
+            error: Some(err),
+        },
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+fn compute_eigenfrequencies(params: &EigenfrequencyParams) -> Result<Vec<f64>, SimError> {
+    if params.n == 0 {
+        return Err(SimError::InvalidParam { field: "n", reason: "must be at least 1".to_string() });
+    }
+    if params.n > MAX_N {
+        return Err(SimError::TooLarge { field: "n", max: MAX_N, got: params.n });
+    }
+
+    let masses = parse_csv_f64("masses", &params.masses)?;
+    let lengths = parse_csv_f64("lengths", &params.lengths)?;
+    check_len("masses", &masses, params.n)?;
+    check_len("lengths", &lengths, params.n)?;
+    check_all_positive("masses", &masses)?;
+    check_all_positive("lengths", &lengths)?;
+    if !(params.gx.is_finite() && params.gy.is_finite()) {
+        let reason = "gravity components must be finite".to_string();
+        return Err(SimError::InvalidParam { field: "gx/gy", reason });
+    }
+
+    let mut full_masses = vec![0.0];
+    full_masses.extend(&masses);
+    let mut full_lengths = vec![0.0];
+    full_lengths.extend(&lengths);
+    NPendulumSolver::new(params.n, full_masses, full_lengths)
+        .with_gravity(params.gx, params.gy)
+        .with_rod_inertia(params.rod_inertia)
+        .normal_mode_frequencies()
+}
+
+/// Accelerations Handler: evaluates the equations of motion once, so clients can run their
+/// own time stepping on top of the physics kernel.
+#[cfg(feature = "server")]
+pub async fn accelerations_handler(params: web::Json<AccelerationParams>) -> Result<HttpResponse> {
+    let response = match compute_accelerations(&params) {
+        Ok(accelerations) => AccelerationResponse { success: true, accelerations, error: None },
+        Err(err) => AccelerationResponse {
+            success: false,
+            accelerations: Vec::new(),
+            error: Some(err),
+        },
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Returns the angular accelerations [α1..αn] at the given state.
+fn compute_accelerations(params: &AccelerationParams) -> Result<Vec<f64>, SimError> {
+    if params.n == 0 {
+        return Err(SimError::InvalidParam { field: "n", reason: "must be at least 1".to_string() });
+    }
+    if params.n > MAX_N {
+        return Err(SimError::TooLarge { field: "n", max: MAX_N, got: params.n });
+    }
+
+    let masses = parse_csv_f64("masses", &params.masses)?;
+    let lengths = parse_csv_f64("lengths", &params.lengths)?;
+    check_len("masses", &masses, params.n)?;
+    check_len("lengths", &lengths, params.n)?;
+    check_all_positive("masses", &masses)?;
+    check_all_positive("lengths", &lengths)?;
+    check_len("state", &params.state, 2 * params.n)?;
+    check_all_finite("state", &params.state)?;
+    if !(params.gx.is_finite() && params.gy.is_finite()) {
+        let reason = "gravity components must be finite".to_string();
+        return Err(SimError::InvalidParam { field: "gx/gy", reason });
+    }
+
+    let mut full_masses = vec![0.0];
+    full_masses.extend(&masses);
+    let mut full_lengths = vec![0.0];
+    full_lengths.extend(&lengths);
+    let solver = NPendulumSolver::new(params.n, full_masses, full_lengths)
+        .with_gravity(params.gx, params.gy)
+        .with_rod_inertia(params.rod_inertia);
+
+    // dy/dt = [ω, α]; only the lower half is new information
+    let dydt = solver.deriv(0.0, &DVector::from_column_slice(&params.state))?;
+    Ok(dydt.rows(params.n, params.n).iter().copied().collect())
+}
+
+/// One `SimParams` field as described by `GET /params`.
+#[derive(Serialize)]
+struct ParamSpec {
+    name: &'static str,
+    #[serde(rename = "type")]
+    kind: &'static str, // "integer", "number", "boolean", "string", "csv", "array" or "object"
+    required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    values: Option<Vec<&'static str>>, // Allowed values for enumerations
+    description: &'static str,
+}
+
+impl ParamSpec {
+    fn new(name: &'static str, kind: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            required: false,
+            default: None,
+            min: None,
+            max: None,
+            values: None,
+            description,
+        }
+    }
+
+    fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    fn default(mut self, value: impl Serialize) -> Self {
+        self.default = serde_json::to_value(value).ok();
+        self
+    }
+
+    fn range(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    fn one_of(mut self, values: Vec<&'static str>) -> Self {
+        self.values = Some(values);
+        self
+    }
+}
+
+/// Hand-maintained description of `SimParams`; update it alongside the struct.
+/// CSV fields hold one value per link. `n`, `masses`, `lengths` and `initial_angles` are
+/// required unless a `preset` supplies them.
+fn param_specs() -> Vec<ParamSpec> {
+    let max_n = MAX_N as f64;
+    let max_points = MAX_POINTS as f64;
+    vec![
+        ParamSpec::new("preset", "string", "Fills in n/masses/lengths/initial_angles if unset")
+            .one_of(PRESETS.iter().map(|p| p.name).collect()),
+        ParamSpec::new("n", "integer", "Number of links").range(Some(1.0), Some(max_n)),
+        ParamSpec::new("masses", "csv", "Bob (or rod) masses, each > 0"),
+        ParamSpec::new("lengths", "csv", "Link lengths, each > 0"),
+        ParamSpec::new("normalize_total_length", "number", "Scale lengths to sum to this (m)")
+            .range(Some(0.0), None),
+        ParamSpec::new(
+            "initial_angles",
+            "csv",
+            "Initial angles from straight down (in angle_unit), or horizontal/vertical_(down|up)",
+        ),
+        ParamSpec::new("t_max", "number", "Simulation duration (s); negative runs backward")
+            .required(),
+        ParamSpec::new("n_points", "integer", "Number of integration steps (unless dt is given)")
+            .range(Some(2.0), Some(max_points)),
+        ParamSpec::new("dt", "number", "Time step (s); n_points becomes ceil(t_max / dt) + 1")
+            .range(Some(0.0), None),
+        ParamSpec::new("output_stride", "integer", "Record every Nth integration step")
+            .default(default_stride())
+            .range(Some(1.0), None),
+        ParamSpec::new("substeps", "integer", "RK4 substeps per integration step")
+            .default(default_substeps())
+            .range(Some(1.0), None),
+        ParamSpec::new("resume_state", "array", "Full [θ1..θn, ω1..ωn] state (radians) to resume"),
+        ParamSpec::new(
+            "poincare_section",
+            "object",
+            "Record a Poincaré section: { crossing_link, record_link, direction }",
+        ),
+        ParamSpec::new("phase_link", "integer", "Link whose (θ, ω) series to return")
+            .range(Some(1.0), Some(max_n)),
+        ParamSpec::new("lyapunov_epsilon", "number", "θ1 perturbation for the Lyapunov estimate")
+            .range(Some(0.0), None),
+        ParamSpec::new("divergence_epsilon", "number", "θ1 offset of a companion run (f64 only)")
+            .range(Some(0.0), None),
+        ParamSpec::new("reversibility_check", "boolean", "Integrate back and report the residual")
+            .default(false),
+        ParamSpec::new("precision", "string", "Floating-point precision of the integration")
+            .default("f64")
+            .one_of(vec!["f64", "f32"]),
+        ParamSpec::new("min_separation", "number", "Report the first time two bobs get this close")
+            .range(Some(0.0), None),
+        ParamSpec::new("stop_on_collision", "boolean", "Truncate the run at the first collision")
+            .default(false),
+        ParamSpec::new("sample_times", "array", "Times (0 to t_max) to interpolate positions at"),
+        ParamSpec::new("fps", "number", "Output frames exactly 1/fps s apart (interpolated)")
+            .range(Some(0.0), None),
+        ParamSpec::new("gx", "number", "Gravity x-component (m/s²)").default(0.0),
+        ParamSpec::new("gy", "number", "Gravity y-component (m/s²)").default(default_gy()),
+        ParamSpec::new("pivot_x", "number", "Pivot x in output coordinates (m)").default(0.0),
+        ParamSpec::new("pivot_y", "number", "Pivot y in output coordinates (m)").default(0.0),
+        ParamSpec::new("fixed_mask", "array", "Per-link booleans; true holds the initial angle"),
+        ParamSpec::new("max_ang_vel", "number", "Stop as unstable once any |ω| exceeds this (rad/s)")
+            .range(Some(0.0), None),
+        ParamSpec::new("measure_period", "boolean", "Measure the oscillation period of θ1")
+            .default(false),
+        ParamSpec::new("flip_detection", "boolean", "n = 2: first time θ2 goes over the top")
+            .default(false),
+        ParamSpec::new("spring", "boolean", "Elastic links; lengths become rest lengths")
+            .default(false),
+        ParamSpec::new("stiffness", "csv", "Spring constants (N/m), each > 0; spring mode only"),
+        ParamSpec::new("rod_inertia", "boolean", "Links are uniform rods instead of point bobs")
+            .default(false),
+        ParamSpec::new("gravity_enabled", "boolean", "false switches gravity off entirely")
+            .default(default_gravity_enabled()),
+        ParamSpec::new("gravity_radius", "number", "Planet center distance (m); g ∝ 1/r² from there")
+            .range(Some(0.0), None),
+        ParamSpec::new("reproducible", "boolean", "Omit wall-clock fields (byte-identical output)")
+            .default(false),
+        ParamSpec::new("tip_trace", "boolean", "Also return the last bob's path as `tip_path`")
+            .default(false),
+        ParamSpec::new("flat", "boolean", "Return positions as one flat array with stride")
+            .default(false),
+        ParamSpec::new("topology", "csv", "Parent link per link (0 = pivot, < own index)"),
+        ParamSpec::new("compute_tensions", "boolean", "Also return each link's axial force (N)")
+            .default(false),
+        ParamSpec::new("compute_velocities", "boolean", "Also return each bob's (ẋ, ẏ) per frame")
+            .default(false),
+        ParamSpec::new("compute_lagrangian", "boolean", "Also return L = T − V and H = T + V (J)")
+            .default(false),
+        ParamSpec::new("compute_condition_number", "boolean", "Also return cond(M) per frame")
+            .default(false),
+        ParamSpec::new("compute_tip_speed", "boolean", "Also return the last bob's peak speed")
+            .default(false),
+        ParamSpec::new("compute_gravity_work", "boolean", "Also return gravity's work (J)")
+            .default(false),
+        ParamSpec::new("quadrature", "string", "Rule for gravity_work and dissipated_energy")
+            .default("trapezoidal")
+            .one_of(vec!["trapezoidal", "simpson"]),
+        ParamSpec::new("output_frame", "string", "Bob coordinates: (x, y) or (r, φ) about the origin")
+            .default("cartesian")
+            .one_of(vec!["cartesian", "polar"]),
+        ParamSpec::new("energy_correction", "boolean", "Rescale ω each step to hold the energy")
+            .default(false),
+        ParamSpec::new("summary_only", "boolean", "Return scalar `summary` stats, no frames")
+            .default(false),
+        ParamSpec::new("final_only", "boolean", "Return only the last frame and final state")
+            .default(false),
+        ParamSpec::new("error_estimate", "boolean", "Rerun at dt/2 for a Richardson error estimate")
+            .default(false),
+        ParamSpec::new("target_energy", "number", "Start from a random state with this energy (J)"),
+        ParamSpec::new("perturbation_sigma", "number", "Gaussian noise on initial angles")
+            .range(Some(0.0), None),
+        ParamSpec::new("normal_mode", "integer", "Start along this normal mode (0 = lowest)")
+            .range(Some(0.0), None),
+        ParamSpec::new("mode_amplitude", "number", "Largest initial angle of the normal mode")
+            .range(Some(0.0), None),
+        ParamSpec::new("seed", "integer", "Seed for target_energy and perturbation_sigma")
+            .default(0),
+        ParamSpec::new("damping_model", "string", "Joint friction law applied to every link")
+            .default("none")
+            .one_of(vec!["none", "linear", "quadratic", "coulomb"]),
+        ParamSpec::new("damping", "csv", "Friction coefficient per link (b, c or μ; ≥ 0)"),
+        ParamSpec::new("analytic", "boolean", "n = 1: exact elliptic solution instead of RK4")
+            .default(false),
+        ParamSpec::new("angle_unit", "string", "Angle unit of inputs and angle outputs")
+            .default("deg")
+            .one_of(vec!["deg", "rad"]),
+        ParamSpec::new("warmup_time", "number", "Unrecorded burn-in (s); output times start there")
+            .default(0.0)
+            .range(Some(0.0), None),
+    ]
+}
+
+/// Params Handler: describes the `/simulate` parameters so clients can build their forms.
+#[cfg(feature = "server")]
+pub async fn params_handler() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(param_specs()))
+}
+
+/// Scenario served by `/default_scenario` when no file is configured or it cannot be used.
+#[cfg(feature = "server")]
+const BUILTIN_SCENARIO: &str = r#"
+preset = "double_chaos"
+t_max = 20.0
+n_points = 2001
+"#;
+
+/// Scenario the landing page starts with (app data, see `main`), with any preset filled in.
+#[cfg(feature = "server")]
+pub struct DefaultScenario(SimParams);
+
+#[cfg(feature = "server")]
+impl DefaultScenario {
+    /// Loads the scenario file at `path` (TOML, or JSON for a `.json` extension). A missing
+    /// path, unreadable file or invalid scenario falls back to `BUILTIN_SCENARIO` with a
+    /// warning, so a bad file never keeps the server from starting.
+    pub fn load(path: Option<&str>) -> Self {
+        let builtin = || {
+            let params = parse_scenario(BUILTIN_SCENARIO, false);
+            DefaultScenario(params.expect("BUILTIN_SCENARIO is a valid scenario"))
+        };
+        let Some(path) = path else {
+            return builtin();
+        };
+        let is_json = Path::new(path).extension().is_some_and(|ext| ext == "json");
+        match fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| parse_scenario(&text, is_json))
+        {
+            Ok(params) => {
+                tracing::info!("Default scenario loaded from {path}");
+                DefaultScenario(params)
+            }
+            Err(err) => {
+                tracing::warn!("Ignoring scenario file {path} ({err}); using the built-in scenario");
+                builtin()
+            }
+        }
+    }
+}
+
+/// Parses a scenario into `SimParams` and resolves its preset, so clients receive explicit
+/// `n`, `masses`, `lengths` and `initial_angles`.
+#[cfg(feature = "server")]
+fn parse_scenario(text: &str, is_json: bool) -> Result<SimParams, String> {
+    let params: SimParams = if is_json {
+        serde_json::from_str(text).map_err(|err| err.to_string())?
+    } else {
+        toml::from_str(text).map_err(|err| err.to_string())?
+    };
+    check_positive("t_max", params.t_max).map_err(|err| err.to_string())?;
+    if params.dt.is_none() && params.n_points < 2 {
+        return Err("needs `dt` or `n_points` of at least 2".to_string());
+    }
+    match &params.preset {
+        Some(name) => params.with_preset(name).map_err(|err| err.to_string()),
+        None => Ok(params),
+    }
+}
+
+/// Default Scenario Handler: the parameters the landing page should simulate first.
+#[cfg(feature = "server")]
+pub async fn default_scenario_handler(scenario: web::Data<DefaultScenario>) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(&scenario.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(json: serde_json::Value) -> SimParams {
+        serde_json::from_value(json).expect("valid SimParams")
+    }
+
+    #[test]
+    fn non_finite_output_becomes_a_clean_error() {
+        // θ1 + 1e-300 == θ1, so the Lyapunov separation is 0 and ln(0) = −inf
+        let params = params(serde_json::json!({
+            "preset": "double",
+            "t_max": 1.0,
+            "n_points": 5,
+            "lyapunov_epsilon": 1e-300,
+        }));
+        let response = simulate(&params, &mut |_| {}, None);
+        assert!(!response.success);
+        assert_eq!(response.error, Some(SimError::NonFinite { field: "lyapunov" }));
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["error"]["code"], "non_finite");
+        assert!(json["animation_data"]["positions"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn singular_mass_matrix_becomes_a_clean_error() {
+        // M22 = m2 l2² and M12² both underflow to zero, so the second LU pivot is exactly 0
+        let params = params(serde_json::json!({
+            "n": 2,
+            "masses": "1,1",
+            "lengths": "1,1e-200",
+            "initial_angles": "30,30",
+            "t_max": 1.0,
+            "n_points": 5,
+        }));
+        let response = simulate(&params, &mut |_| {}, None);
+        assert!(!response.success);
+        assert_eq!(response.error, Some(SimError::SingularMatrix));
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["error"]["code"], "singular_matrix");
+        assert!(json["animation_data"]["positions"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn single_pendulum_center_of_mass_is_the_bob() {
+        let params = params(serde_json::json!({
+            "n": 1,
+            "masses": "2.5",
+            "lengths": "1.3",
+            "initial_angles": "70",
+            "t_max": 2.0,
+            "n_points": 41,
+        }));
+        let response = simulate(&params, &mut |_| {}, None);
+        assert!(response.success, "{:?}", response.error);
+        let data = response.animation_data;
+        let Positions::Nested(frames) = &data.positions else { panic!("nested positions") };
+        assert_eq!(frames.len(), 41);
+        assert_eq!(data.center_of_mass.len(), frames.len());
+        for (com, bob) in data.center_of_mass.iter().zip(frames) {
+            assert!((com[0] - bob[0]).abs() < 1e-12 && (com[1] - bob[1]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn finite_runs_still_succeed() {
+        let params = params(serde_json::json!({ "preset": "double", "t_max": 1.0, "n_points": 5 }));
+        let response = simulate(&params, &mut |_| {}, None);
+        assert!(response.success, "{:?}", response.error);
+    }
+
+    #[test]
+    #[cfg(feature = "server")] // Goes through the actix query parser
+    fn non_finite_inputs_are_rejected_before_solving() {
+        let nan_length = params(serde_json::json!({
+            "preset": "triple",
+            "lengths": "1,nan,1",
+            "t_max": 1.0,
+            "n_points": 5,
+        }));
+        let response = simulate(&nan_length, &mut |_| {}, None);
+        assert!(!response.success);
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["error"]["code"], "invalid_param");
+        assert_eq!(json["error"]["detail"], "lengths: 'nan' is not a finite number");
+
+        // Query strings parse "inf" straight into the f64 fields
+        let query = "preset=double&t_max=inf&n_points=5";
+        let inf_duration = web::Query::<SimParams>::from_query(query).unwrap().into_inner();
+        let response = simulate(&inf_duration, &mut |_| {}, None);
+        assert_eq!(
+            response.error,
+            Some(SimError::InvalidParam { field: "t_max", reason: "must be a finite number".into() })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "server")] // Uses the server's `SolveTimeout`
+    fn convergence_error_shrinks_towards_the_reference() {
+        let study = ConvergenceParams {
+            params: params(serde_json::json!({ "preset": "double_chaos", "t_max": 1.0 })),
+            resolutions: vec![400, 25, 100, 100],
+        };
+        let deadline = SolveTimeout(Duration::from_secs(60)).deadline();
+        let rows = run_convergence_study(&study, deadline).unwrap();
+        let n_points: Vec<_> = rows.iter().map(|r| r.n_points).collect();
+        assert_eq!(n_points, [25, 100, 400]);
+        assert!(rows[0].error > rows[1].error && rows[1].error > 0.0);
+        assert_eq!(rows[2].error, 0.0);
+    }
+
+    #[test]
+    fn oversized_csv_is_refused_before_parsing() {
+        let huge = "1,".repeat(MAX_N + 1);
+        assert_eq!(
+            parse_csv_f64("masses", &huge),
+            Err(SimError::TooLarge { field: "masses", max: MAX_N, got: MAX_N + 1 })
+        );
+        assert_eq!(parse_csv_f64("masses", &"1,".repeat(MAX_N)).unwrap().len(), MAX_N);
+    }
+
+    #[test]
+    fn simulate_json_matches_the_response_envelope() {
+        let body = simulate_json(r#"{ "preset": "double", "t_max": 1.0, "n_points": 5 }"#);
+        let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(response["success"], true, "{body}");
+        assert_eq!(response["animation_data"]["n"], 2);
+
+        let body = simulate_json("{ not json");
+        let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(response["success"], false);
+        assert_eq!(response["error"]["code"], "invalid_param");
+    }
+
+    #[test]
+    fn mismatched_lengths_are_errors_not_panics() {
+        let sol = [DVector::from_vec(vec![0.1, 0.2, 0.0, 0.0])];
+        let extra = compute_positions(&sol, 2, &[0.0, 1.0, 1.0, 1.0], &[0, 0, 1]).err();
+        let err = extra.expect("too many lengths");
+        assert_eq!(err, SimError::LengthMismatch { field: "lengths", expected: 2, got: 3 });
+        assert!(err.to_string().contains("too many values"), "{err}");
+        let short = compute_positions(&sol, 2, &[0.0, 1.0], &[0, 0, 1]).err();
+        assert!(short.expect("too few lengths").to_string().contains("too few values"));
+        assert!(compute_positions(&sol, 2, &[0.0, 1.0, 1.0], &[0, 0, 2]).is_err());
+        assert_eq!(compute_positions(&sol, 2, &[0.0, 1.0, 1.0], &[0, 0, 1]).unwrap().len(), 1);
+
+        let solver = NPendulumSolver::new(2, vec![0.0, 1.0, 1.0], vec![0.0, 1.0, 1.0]);
+        let err = solver.initial_state(&[0.0, 0.1, 0.2, 0.3], &[0.0; 3]).err();
+        assert_eq!(
+            err,
+            Some(SimError::LengthMismatch { field: "initial_angles", expected: 2, got: 3 })
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn msgpack_body_round_trips_to_the_json_response() {
+        let params = params(serde_json::json!({
+            "preset": "double",
+            "t_max": 0.5,
+            "n_points": 11,
+            "phase_link": 1,
+            "lyapunov_epsilon": 1e-8,
+        }));
+        let response = simulate(&params, &mut |_| {}, None);
+        let encoded = encode(&response, MSGPACK_MIME).unwrap();
+        assert_eq!(encoded.content_type, MSGPACK_MIME);
+        let decoded: SimResponse = rmp_serde::from_slice(&encoded.bytes).unwrap();
+        assert!(decoded.success);
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&response).unwrap()
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn accept_header_selects_msgpack_only_when_listed() {
+        let format = |accept: Option<&str>| {
+            let mut req = actix_web::test::TestRequest::default();
+            if let Some(accept) = accept {
+                req = req.insert_header((header::ACCEPT, accept));
+            }
+            response_format(&req.to_http_request())
+        };
+        assert_eq!(format(None), "application/json");
+        assert_eq!(format(Some("application/json")), "application/json");
+        assert_eq!(format(Some("*/*")), "application/json");
+        assert_eq!(format(Some("application/msgpack-ext")), "application/json");
+        assert_eq!(format(Some("application/msgpack")), MSGPACK_MIME);
+        assert_eq!(format(Some("text/html, application/msgpack;q=0.9")), MSGPACK_MIME);
+    }
+
+    #[test]
+    fn reproducible_responses_are_byte_identical() {
+        let ones = |n: usize| vec!["1"; n].join(",");
+        // Both sides of the parallel assembly threshold
+        for n in [3, crate::math::PARALLEL_THRESHOLD + 4] {
+            let params = params(serde_json::json!({
+                "n": n,
+                "masses": ones(n),
+                "lengths": ones(n),
+                "initial_angles": "horizontal",
+                "t_max": 0.5,
+                "n_points": 51,
+                "reproducible": true,
+            }));
+            let run = || serde_json::to_vec(&simulate(&params, &mut |_| {}, None)).unwrap();
+            let first = run();
+            assert!(String::from_utf8_lossy(&first).contains("\"success\":true"));
+            assert_eq!(first, run(), "n = {n}");
+        }
+    }
+
+    #[test]
+    fn angle_keywords_expand_to_straight_chains() {
+        use std::f64::consts::{FRAC_PI_2, PI};
+        let (deg, rad) = (AngleUnit::Deg, AngleUnit::Rad);
+        assert_eq!(parse_initial_angles("horizontal", 3, deg).unwrap(), [FRAC_PI_2; 3]);
+        assert_eq!(parse_initial_angles(" vertical_up ", 2, rad).unwrap(), [PI; 2]);
+        assert_eq!(parse_initial_angles("30, -90", 2, deg).unwrap(), [PI / 6.0, -FRAC_PI_2]);
+        assert_eq!(parse_initial_angles("0.5, -1", 2, rad).unwrap(), [0.5, -1.0]);
+        assert!(matches!(
+            parse_initial_angles("sideways", 2, deg),
+            Err(SimError::ParseError { field: "initial_angles", .. })
+        ));
+    }
+
+    #[test]
+    fn oversized_n_is_rejected_before_a_keyword_expands() {
+        let params = params(serde_json::json!({
+            "n": 1_000_000_000_000_u64,
+            "initial_angles": "horizontal",
+            "t_max": 1.0,
+            "n_points": 2,
+        }));
+        let err = run_simulation(&params, &mut |_| {}, None).err();
+        assert!(matches!(err, Some(SimError::TooLarge { field: "n", .. })), "{err:?}");
+    }
+
+    #[test]
+    fn angle_unit_applies_to_inputs_and_angle_outputs() {
+        let run = |angles: &str, unit: Option<&str>| {
+            let mut json = serde_json::json!({
+                "preset": "double",
+                "initial_angles": angles,
+                "t_max": 0.5,
+                "n_points": 11,
+                "phase_link": 1,
+                "reproducible": true,
+            });
+            if let Some(unit) = unit {
+                json["angle_unit"] = unit.into();
+            }
+            serde_json::to_value(simulate(&params(json), &mut |_| {}, None)).unwrap()
+                ["animation_data"]
+                .clone()
+        };
+        let default = run("90,45", None);
+        let radians = run("1.5707963267948966,0.7853981633974483", Some("rad"));
+        let degrees = run("90,45", Some("deg"));
+        // Spelling out the default changes nothing
+        assert_eq!(default, degrees);
+        assert_eq!(degrees["positions"], radians["positions"]);
+
+        let start = |data: &serde_json::Value| data["phase_space"][0][0].as_f64().unwrap();
+        assert!((start(&radians) - std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+        assert!((start(&degrees) - 90.0).abs() < 1e-12);
+        // The ω bounds scale the phase plot's axes, so they follow the unit too
+        let bounds = |data: &serde_json::Value| -> Vec<[f64; 2]> {
+            serde_json::from_value(data["velocity_bounds"].clone()).unwrap()
+        };
+        for (deg, rad) in bounds(&degrees).iter().flatten().zip(bounds(&radians).iter().flatten()) {
+            assert!((deg - rad.to_degrees()).abs() < 1e-9, "{deg} vs {rad}");
+        }
+
+        let unknown = serde_json::from_value::<SimParams>(serde_json::json!({
+            "t_max": 1.0,
+            "angle_unit": "degrees",
+        }));
+        let error = unknown.err().expect("unknown unit rejected").to_string();
+        assert!(error.contains("expected `deg` or `rad`"), "{error}");
+    }
+
+    #[test]
+    fn bob_velocities_are_the_derivative_of_positions() {
+        // Straight horizontal chain swinging down at ω = 1: the second bob moves at 2 m/s
+        let params = params(serde_json::json!({
+            "n": 2,
+            "masses": "1,1",
+            "lengths": "1,1",
+            "resume_state": [std::f64::consts::FRAC_PI_2, std::f64::consts::FRAC_PI_2, 1.0, 1.0],
+            "t_max": 0.1,
+            "n_points": 2,
+            "compute_velocities": true,
+        }));
+        let response = simulate(&params, &mut |_| {}, None);
+        let velocities = response.animation_data.bob_velocities.unwrap();
+        let expected = [0.0, 1.0, 0.0, 2.0];
+        assert!(velocities[0].iter().zip(expected).all(|(v, e)| (v - e).abs() < 1e-12));
+    }
+
+    #[test]
+    fn frame_times_follow_the_output_stride() {
+        let params = params(serde_json::json!({
+            "preset": "double",
+            "t_max": 1.0,
+            "n_points": 5,
+            "output_stride": 2,
+        }));
+        let response = simulate(&params, &mut |_| {}, None);
+        let json = serde_json::to_value(&response).unwrap();
+        let frames = json["animation_data"]["positions"].as_array().unwrap().len();
+        let times: Vec<f64> = serde_json::from_value(json["animation_data"]["times"].clone()).unwrap();
+        assert_eq!(times.len(), frames);
+        assert_eq!(times, [0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn fps_frames_are_evenly_timed_and_match_the_solver_frames() {
+        let request = |extra: serde_json::Value| {
+            let mut json =
+                serde_json::json!({ "preset": "double", "t_max": 1.05, "n_points": 106 });
+            json.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+            let json = serde_json::to_value(simulate(&params(json), &mut |_| {}, None)).unwrap();
+            json["animation_data"].clone()
+        };
+        let solver = request(serde_json::json!({}));
+        let video = request(serde_json::json!({ "fps": 10.0 }));
+        // The last frame due at or before t_max (1.0 s here) is included
+        let times: Vec<f64> = serde_json::from_value(video["times"].clone()).unwrap();
+        assert_eq!(times.len(), 11);
+        assert!(times.iter().enumerate().all(|(k, &t)| (t - k as f64 / 10.0).abs() < 1e-12));
+        // dt = 0.01, so every tenth solver frame falls on a video frame
+        let positions = |data: &serde_json::Value, frame: usize| -> Vec<f64> {
+            serde_json::from_value(data["positions"][frame].clone()).unwrap()
+        };
+        for k in 0..times.len() {
+            let (a, b) = (positions(&video, k), positions(&solver, 10 * k));
+            assert!(a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 1e-9), "frame {k}");
+        }
+
+        let params = params(serde_json::json!({
+            "preset": "double",
+            "t_max": 1.0,
+            "n_points": 101,
+            "fps": 0.0,
+        }));
+        let err = run_simulation(&params, &mut |_| {}, None).err().unwrap();
+        assert_eq!(err.code(), "invalid_param");
+    }
+
+    #[test]
+    fn analytic_single_pendulum_matches_the_integrated_run() {
+        let request = |analytic: bool| {
+            let params = params(serde_json::json!({
+                "n": 1,
+                "masses": "1",
+                "lengths": "1",
+                "initial_angles": "120",
+                "t_max": 3.0,
+                "n_points": 301,
+                "measure_period": true,
+                "analytic": analytic,
+            }));
+            serde_json::to_value(simulate(&params, &mut |_| {}, None)).unwrap()
+        };
+        let (exact, numeric) = (request(true), request(false));
+        assert_eq!(exact["success"], true);
+
+        let frame = |json: &serde_json::Value| -> Vec<Vec<f64>> {
+            serde_json::from_value(json["animation_data"]["positions"].clone()).unwrap()
+        };
+        for (a, b) in frame(&exact).iter().zip(frame(&numeric)) {
+            assert!((a[0] - b[0]).abs() < 1e-6 && (a[1] - b[1]).abs() < 1e-6, "{a:?} vs {b:?}");
+        }
+        let period = |key: &str| exact["animation_data"][key].as_f64().unwrap();
+        assert!((period("period") - period("exact_period")).abs() < 1e-8);
+    }
+
+    #[test]
+    fn normalized_lengths_sum_to_the_requested_total() {
+        let params = params(serde_json::json!({
+            "n": 2,
+            "masses": "1,1",
+            "lengths": "1,3",
+            "initial_angles": "0,0",
+            "t_max": 0.1,
+            "n_points": 2,
+            "normalize_total_length": 1.0,
+        }));
+        let json = serde_json::to_value(simulate(&params, &mut |_| {}, None)).unwrap();
+        let data = &json["animation_data"];
+        assert_eq!(data["length_scale"], 0.25);
+        // Hanging straight down, the tip sits the total length below the pivot
+        let tip = data["positions"][0][3].as_f64().unwrap();
+        assert!((tip + 1.0).abs() < 1e-12, "{tip}");
+        assert_eq!(data["limit"], 1.5);
+    }
+
+    #[test]
+    fn tip_speed_peaks_at_the_bottom_of_the_swing() {
+        let request = |extra: serde_json::Value| {
+            let mut json = serde_json::json!({
+                "n": 1,
+                "masses": "1",
+                "lengths": "1",
+                "initial_angles": "120",
+                "t_max": 3.0,
+                "n_points": 301,
+                "output_stride": 50,
+                "compute_tip_speed": true,
+            });
+            json.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+            let json = serde_json::to_value(simulate(&params(json), &mut |_| {}, None)).unwrap();
+            json["animation_data"].clone()
+        };
+        let data = request(serde_json::json!({ "measure_period": true, "analytic": true }));
+        let quarter = data["exact_period"].as_f64().unwrap() / 4.0;
+        // Energy conservation: v² = 2gL(1 − cos θ0) at the bottom, a quarter period in
+        let expected = (2.0 * 9.81 * 1.5_f64).sqrt();
+        let data = request(serde_json::json!({}));
+        let value = |key: &str| data[key].as_f64().unwrap();
+        let (speed, time) = (value("max_tip_speed"), value("max_tip_speed_time"));
+        assert!((speed - expected).abs() < 1e-3 * expected, "{speed} vs {expected}");
+        assert!((time - quarter).abs() <= 0.01, "{time} vs {quarter}");
+
+        let summary = request(serde_json::json!({ "summary_only": true }));
+        assert_eq!(summary["max_tip_speed"], summary["summary"]["max_tip_speed"]);
+    }
+
+    #[test]
+    fn gravity_work_matches_the_drop_in_potential_energy() {
+        let params = params(serde_json::json!({
+            "preset": "double_chaos",
+            "t_max": 2.0,
+            "n_points": 2001,
+            "compute_lagrangian": true,
+            "compute_gravity_work": true,
+        }));
+        let json = serde_json::to_value(simulate(&params, &mut |_| {}, None)).unwrap();
+        let data = &json["animation_data"];
+        // H − L = 2V, so the work gravity did is −ΔV = −½ Δ(H − L)
+        let potential = |i: usize| {
+            let at = |key: &str| data[key][i].as_f64().unwrap();
+            0.5 * (at("hamiltonian") - at("lagrangian"))
+        };
+        let last = data["lagrangian"].as_array().unwrap().len() - 1;
+        let expected = potential(0) - potential(last);
+        let work = data["gravity_work"].as_f64().unwrap();
+        assert!(expected.abs() > 1.0, "the run should exchange energy: {expected}");
+        assert!((work - expected).abs() < 1e-3 * expected.abs(), "{work} vs {expected}");
+    }
+
+    #[test]
+    fn small_normal_mode_start_oscillates_at_its_eigenfrequency() {
+        let root2 = 2.0_f64.sqrt();
+        for (mode, ratio) in [(0, root2), (1, -root2)] {
+            let params = params(serde_json::json!({
+                "n": 2,
+                "masses": "1,1",
+                "lengths": "1,1",
+                "t_max": 10.0,
+                "n_points": 2001,
+                "normal_mode": mode,
+                "mode_amplitude": 0.5,
+                "measure_period": true,
+            }));
+            let json = serde_json::to_value(simulate(&params, &mut |_| {}, None)).unwrap();
+            let data = &json["animation_data"];
+            let start = &data["initial_state"];
+            let (theta1, theta2) = (start[0].as_f64().unwrap(), start[1].as_f64().unwrap());
+            assert!((theta2 / theta1 - ratio).abs() < 1e-9, "mode {mode}: {theta2} / {theta1}");
+            // Equal double pendulum: ω² = (2 ∓ √2) g / l
+            let omega = ((2.0 - ratio) * 9.81_f64).sqrt();
+            let expected = 2.0 * std::f64::consts::PI / omega;
+            let period = data["period"].as_f64().unwrap();
+            assert!((period - expected).abs() < 1e-3 * expected, "{period} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn dissipated_energy_matches_the_energy_lost() {
+        for quadrature in ["trapezoidal", "simpson"] {
+            let params = params(serde_json::json!({
+                "preset": "double_chaos",
+                "t_max": 2.0,
+                "n_points": 2001,
+                "damping_model": "linear",
+                "damping": "0.3,0.2",
+                "compute_lagrangian": true,
+                "quadrature": quadrature,
+            }));
+            let json = serde_json::to_value(simulate(&params, &mut |_| {}, None)).unwrap();
+            let data = &json["animation_data"];
+            let energy: Vec<f64> = serde_json::from_value(data["hamiltonian"].clone()).unwrap();
+            let lost = energy[0] - energy[energy.len() - 1];
+            let dissipated = data["dissipated_energy"].as_f64().unwrap();
+            assert!(lost > 1.0, "the friction should drain energy: {lost}");
+            let error = (dissipated - lost).abs();
+            assert!(error < 1e-3 * lost, "{quadrature}: {dissipated} vs {lost}");
+        }
+    }
+
+    #[test]
+    fn final_only_returns_the_last_frame_of_the_full_run() {
+        let run = |final_only: bool| {
+            let params = params(serde_json::json!({
+                "preset": "double_chaos",
+                "t_max": 2.0,
+                "n_points": 201,
+                "compute_velocities": true,
+                "final_only": final_only,
+            }));
+            let json = serde_json::to_value(simulate(&params, &mut |_| {}, None)).unwrap();
+            json["animation_data"].clone()
+        };
+        let (full, last) = (run(false), run(true));
+        for key in ["positions", "times", "bob_velocities", "angular_momentum"] {
+            let frames = full[key].as_array().unwrap();
+            assert_eq!(last[key].as_array().unwrap(), &frames[frames.len() - 1..], "{key}");
+        }
+        assert_eq!(last["final_state"], full["final_state"]);
+        // Measured between the first and last states only
+        let drift = |data: &serde_json::Value| data["energy_drift"].as_f64().unwrap();
+        assert!(drift(&last) <= drift(&full));
+    }
+
+    #[test]
+    fn went_over_top_means_some_bob_rose_above_the_pivot() {
+        let over_top = |angles: &str| {
+            let params = params(serde_json::json!({
+                "n": 2,
+                "masses": "1,1",
+                "lengths": "1,1",
+                "initial_angles": angles,
+                "t_max": 2.0,
+                "n_points": 401,
+                "summary_only": true,
+            }));
+            let json = serde_json::to_value(simulate(&params, &mut |_| {}, None)).unwrap();
+            json["animation_data"]["summary"]["went_over_top"].as_bool().unwrap()
+        };
+        // Too little energy to lift either bob to the pivot's height
+        assert!(!over_top("30,0"));
+        // Released above the pivot without any link passing straight up
+        assert!(over_top("100,100"));
+    }
+
+    #[test]
+    fn flip_detection_reports_the_first_flip_of_the_lower_link() {
+        let run = |angles: &str| {
+            let params = params(serde_json::json!({
+                "preset": "double",
+                "initial_angles": angles,
+                "t_max": 10.0,
+                "n_points": 1001,
+                "flip_detection": true,
+                "summary_only": true,
+            }));
+            serde_json::to_value(simulate(&params, &mut |_| {}, None)).unwrap()["animation_data"]
+                ["flip_time"]
+                .as_f64()
+        };
+        // Too little energy to ever reach the top, then a near-inverted start that does
+        assert_eq!(run("30,30"), None);
+        let flip = run("170,170").expect("flips");
+        assert!(flip > 0.0 && flip < 10.0, "flipped at {flip}");
+    }
+
+    #[test]
+    fn damping_coefficients_are_validated_against_the_model() {
+        let run = |model: &str, damping: &str, energy_correction: bool| {
+            let params = params(serde_json::json!({
+                "preset": "double",
+                "t_max": 1.0,
+                "n_points": 5,
+                "damping_model": model,
+                "damping": damping,
+                "energy_correction": energy_correction,
+            }));
+            simulate(&params, &mut |_| {}, None).error
+        };
+        let invalid =
+            |field, reason: &str| Some(SimError::InvalidParam { field, reason: reason.into() });
+
+        assert_eq!(run("linear", "0.1,0.2", false), None);
+        assert_eq!(run("none", "0.1,0.2", false), invalid("damping", "requires a damping_model"));
+        assert_eq!(
+            run("coulomb", "0.1", false),
+            Some(SimError::LengthMismatch { field: "damping", expected: 2, got: 1 })
+        );
+        assert_eq!(run("quadratic", "0.1,-1", false).map(|e| e.code()), Some("invalid_param"));
+        assert_eq!(
+            run("linear", "0.1,0.2", true),
+            invalid("energy_correction", "cannot be combined with damping_model")
+        );
+    }
+}
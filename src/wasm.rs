@@ -0,0 +1,10 @@
+// src/wasm.rs
+use crate::ui;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Browser entry point: takes the `/simulate` parameters as JSON and returns the same JSON
+/// body the server would, computed client-side. There is no time limit, so keep requests small.
+#[wasm_bindgen]
+pub fn simulate(params_json: &str) -> String {
+    ui::simulate_json(params_json)
+}
@@ -264,7 +264,7 @@ els.btns.run.addEventListener('click', () => {
     })
     .then(res => res.ok ? res.json() : res.text().then(t => { throw new Error(t) }))
     .then(data => {
-        if (!data.success) throw new Error(data.message || 'Unknown error');
+        if (!data.success) throw new Error(data.error ? data.error.detail : 'Unknown error');
         
         // Success
         state.animData = data.animation_data;